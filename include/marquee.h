@@ -0,0 +1,47 @@
+/*
+ * The C API for marquee, which works out the frames of a marquee without printing them.
+ *
+ * Build the library with `cargo rustc --release --lib --features ffi --crate-type cdylib`.
+ */
+
+#ifndef MARQUEE_H
+#define MARQUEE_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct marquee marquee_t;
+
+/*
+ * Create a marquee from `argc` command line arguments (without the name of the program), which
+ * are the same as the ones that the `marquee` command takes.  Returns NULL if the arguments
+ * aren't valid, or if anything else went wrong.
+ */
+marquee_t *marquee_new(const char *const *argv, size_t argc);
+
+/*
+ * Show `text` on the marquee, this is the same as a line of input to the `marquee` command.
+ * Returns 0 if the text was shown, or -1 if `marquee` or `text` is NULL or anything else went
+ * wrong.
+ */
+int marquee_set_text(marquee_t *marquee, const char *text);
+
+/*
+ * Work out the next frame, with the rows joined by `\n`.  Returns NULL if there is nothing to
+ * show.  The string is valid until the next call to `marquee_next_frame` or `marquee_free`.
+ */
+const char *marquee_next_frame(marquee_t *marquee);
+
+/*
+ * Free a marquee that was created with `marquee_new`.
+ */
+void marquee_free(marquee_t *marquee);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
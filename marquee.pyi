@@ -0,0 +1,18 @@
+"""Type hints for the Python module, which is built with the `python` feature"""
+
+from typing import Optional, Sequence
+
+class Marquee:
+    """A marquee that the frames can be taken from one at a time"""
+
+    delay: float
+    """The number of seconds to wait between each frame"""
+
+    def __init__(self, args: Sequence[str] = ...) -> None:
+        """Create a marquee from the same arguments that the `marquee` command takes"""
+
+    def set_text(self, text: str) -> None:
+        """Show `text` on the marquee, this is the same as a line of input to the command"""
+
+    def next_frame(self) -> Optional[str]:
+        """Work out the next frame, the rows are joined with a newline"""
@@ -0,0 +1,110 @@
+//! Timing how long it takes to work out frames of the marquee (see `marquee bench`)
+
+#[cfg(feature = "bench")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::{
+    hint::black_box,
+    io::{self, Write},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use crate::{
+    error::{Error, Exit},
+    figlet::Font,
+    marquee::Marquee,
+    Cli,
+};
+
+/// The content that is repeated to make up each length that is timed
+const TEXT: &str = "The quick brown fox jumps over the lazy dog. ";
+
+/// The number of allocations that have been made since the process started
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, but it counts the allocations that are made so that `marquee bench` can
+/// report them.  The binary only sets it as the `#[global_allocator]` with the `bench` feature,
+/// since it makes every allocation a little slower.
+#[cfg(feature = "bench")]
+pub struct CountingAlloc;
+
+#[cfg(feature = "bench")]
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// The number of allocations that have been made so far, if they are being counted
+fn allocations() -> Option<usize> {
+    cfg!(feature = "bench").then(|| ALLOCATIONS.load(Ordering::Relaxed))
+}
+
+/// Work out `frames` frames for each combination of `lengths` and `widths`, without waiting
+/// between them or printing them, and print how many frames were worked out per second
+pub fn bench(
+    options: &Cli,
+    frames: usize,
+    lengths: &[usize],
+    widths: &[usize],
+) -> Result<Exit, Error> {
+    let mut out = io::stdout().lock();
+    writeln!(
+        out,
+        "{:>8} {:>6} {:>12} {:>13}",
+        "length", "width", "frames/s", "allocs/frame"
+    )
+    .map_err(Error::Output)?;
+
+    for &length in lengths {
+        let text: String = TEXT.chars().cycle().take(length).collect();
+        // With `--json` each message is JSON, so the text is the content of one
+        let content = match options.json {
+            true => serde_json::json!({ "content": text }).to_string(),
+            false => text,
+        };
+        for &width in widths {
+            let mut options = options.clone();
+            options.width = width;
+            options.total_width = None;
+            let font = Font::from_options(&options).map_err(Error::Font)?;
+            let mut marquee = Marquee::new(options, font);
+            marquee.push(content.clone(), None);
+
+            let before = allocations();
+            let start = Instant::now();
+            for _ in 0..frames {
+                black_box(marquee.frame()?);
+            }
+            let elapsed = start.elapsed();
+            // The allocations are only counted with the `bench` feature
+            let per_frame = match (before, allocations()) {
+                (Some(before), Some(after)) => {
+                    format!("{:.1}", (after - before) as f64 / frames.max(1) as f64)
+                }
+                _ => String::from("-"),
+            };
+
+            writeln!(
+                out,
+                "{:>8} {:>6} {:>12.0} {:>13}",
+                length,
+                width,
+                frames as f64 / elapsed.as_secs_f64(),
+                per_frame
+            )
+            .map_err(Error::Output)?;
+        }
+    }
+    Ok(Exit::Finished)
+}
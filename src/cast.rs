@@ -0,0 +1,79 @@
+//! Exporting the marquee as an asciinema cast (see `--export-cast`)
+
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    mem,
+    path::Path,
+    time::Duration,
+};
+
+use crate::{
+    error::{Error, Exit},
+    export,
+    output::Printer,
+    text::visible_len,
+    Cli,
+};
+
+/// The first line of an asciinema v2 cast
+#[derive(Serialize, Debug)]
+struct Header {
+    version: u8,
+    width: usize,
+    height: usize,
+}
+
+/// Write `--cycles` cycles of the marquee to the cast at `path` (see `export::frames`).
+///
+/// Nothing is printed and there's no waiting between frames, each frame is put into the cast
+/// `--delay` after the one before it (see `Cli::frame_delay`).
+pub fn export(options: &Cli, path: &Path) -> Result<Exit, Error> {
+    let frames = export::frames(options)?;
+
+    // The frames are worked out first, since the header needs to know how big the terminal is
+    let mut printer = Printer::with_writer(options, Vec::new());
+    let mut output = Vec::new();
+    let mut width = 0;
+    let mut rows = 1;
+    for frame in &frames {
+        printer.print(frame).map_err(Error::Output)?;
+        output.push(mem::take(printer.get_mut()));
+
+        width = frame
+            .rows
+            .iter()
+            .map(|r| visible_len(r))
+            .fold(width, usize::max);
+        rows = rows.max(frame.rows.len());
+    }
+    printer.finish().map_err(Error::Output)?;
+    output.push(mem::take(printer.get_mut()));
+
+    let header = Header {
+        version: 2,
+        width: width.max(1),
+        // Unless `--same-line` is used, each row is followed by a newline, so the cursor needs a
+        // row of its own below the content or the content would be scrolled out of view
+        height: rows + usize::from(!options.same_line),
+    };
+    write_cast(path, &header, &output, options.frame_delay()).map_err(Error::Export)?;
+
+    Ok(Exit::Finished)
+}
+
+/// Write the cast, where each item of `output` is shown `delay` after the one before it
+fn write_cast(path: &Path, header: &Header, output: &[Vec<u8>], delay: Duration) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut out, header)?;
+    writeln!(out)?;
+    for (i, data) in output.iter().enumerate() {
+        // A terminal turns `\n` into `\r\n` when printing, but a cast is played back as it is
+        let data = String::from_utf8_lossy(data).replace('\n', "\r\n");
+        let time = (delay * i as u32).as_secs_f64();
+        serde_json::to_writer(&mut out, &(time, "o", data))?;
+        writeln!(out)?;
+    }
+    out.flush()
+}
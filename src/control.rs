@@ -0,0 +1,100 @@
+//! Changing the marquee while it is running with lines of input (see `--control-sigil`,
+//! `"type": "control"` with `--json`, and `--control-fifo`)
+
+use serde::Deserialize;
+
+use crate::Cli;
+
+/// Something to change about the marquee, with `--json` this is given by `"action"` (i.e.
+/// `{"type": "control", "action": "speed", "delay": 100}`)
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// Set `--delay` to this many milliseconds
+    Speed {
+        delay: u64,
+    },
+    /// Stop moving until `Resume`
+    Pause,
+    Resume,
+    /// Remove every message
+    Clear,
+    /// Skip the rest of the current message, the same as `SIGUSR1`
+    Skip,
+    /// Remove every message other than the one that is being shown (with `--rotate-all`)
+    Flush,
+    /// Set `--separator`
+    #[serde(alias = "sep")]
+    Separator {
+        separator: String,
+    },
+}
+
+/// The field that tells control messages apart from the content with `--json`
+#[derive(Deserialize)]
+struct JsonKind {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+/// The control command in `line`, if it is one rather than something to show
+pub fn from_line(options: &Cli, line: &str) -> Option<Result<Action, String>> {
+    if let Some(command) = options
+        .control_sigil
+        .as_deref()
+        .and_then(|sigil| line.strip_prefix(sigil))
+    {
+        return Some(parse(command));
+    }
+
+    let is_control = options.json
+        && serde_json::from_str::<JsonKind>(line)
+            .is_ok_and(|j| j.kind.as_deref() == Some("control"));
+    is_control.then(|| serde_json::from_str(line).map_err(|e| e.to_string()))
+}
+
+/// A control command from `--control-fifo`, which is either written like a control line (without
+/// the sigil) or as JSON (i.e. `{"action": "speed", "delay": 100}`)
+pub fn from_fifo(line: &str) -> Result<Action, String> {
+    if line.trim_start().starts_with('{') {
+        serde_json::from_str(line).map_err(|e| e.to_string())
+    } else {
+        parse(line)
+    }
+}
+
+/// Parse a control line (without the sigil), i.e. `speed 100` or `sep " | "`.
+///
+/// The argument to `sep` can be a JSON string, so that it can start or end with spaces.
+fn parse(line: &str) -> Result<Action, String> {
+    let (command, arg) = match line.trim_start().split_once(' ') {
+        Some((command, arg)) => (command, Some(arg)),
+        None => (line.trim(), None),
+    };
+    let action = match (command, arg) {
+        ("speed", Some(ms)) => Action::Speed {
+            delay: ms
+                .trim()
+                .parse()
+                .map_err(|e| format!("invalid speed: {}", e))?,
+        },
+        ("pause", None) => Action::Pause,
+        ("resume", None) => Action::Resume,
+        ("clear", None) => Action::Clear,
+        ("skip", None) => Action::Skip,
+        ("flush", None) => Action::Flush,
+        ("sep", Some(sep)) if sep.starts_with('"') => Action::Separator {
+            separator: serde_json::from_str(sep)
+                .map_err(|e| format!("invalid separator: {}", e))?,
+        },
+        ("sep", Some(sep)) => Action::Separator {
+            separator: sep.into(),
+        },
+        ("speed" | "sep", None) => return Err(format!("`{}` needs an argument", command)),
+        ("pause" | "resume" | "clear" | "skip" | "flush", Some(_)) => {
+            return Err(format!("`{}` doesn't take an argument", command))
+        }
+        _ => return Err(format!("unknown control command `{}`", command)),
+    };
+    Ok(action)
+}
@@ -0,0 +1,161 @@
+//! The errors that stop the marquee
+
+use std::{fmt, io, process::ExitCode};
+
+/// Why the marquee stopped, which decides the exit code of the process so that scripts can tell
+/// them apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// The content has been shown once with `--no-loop`
+    Finished,
+    /// Every tick has been shown with `--clock external`, this exits with 0 like `Finished`
+    Ticks,
+    /// Stdin was closed with `--exit-on-eof`
+    Eof,
+    /// The marquee ran for `--max-runtime`
+    Timeout,
+    /// No messages came in for `--exit-after-idle`, this exits with 0 like `Finished`
+    Idle,
+    /// Something couldn't be read or written
+    Io,
+    /// The input was invalid (i.e. it wasn't UTF-8, or it was invalid JSON with `--strict`)
+    InvalidInput,
+    /// Stdout was closed (i.e. the output was piped into `head`)
+    BrokenPipe,
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        match exit {
+            Exit::Finished | Exit::Ticks | Exit::Idle => ExitCode::SUCCESS,
+            Exit::Io => ExitCode::from(1),
+            // 2 is used by clap when the arguments are invalid
+            Exit::Eof => ExitCode::from(3),
+            Exit::InvalidInput => ExitCode::from(4),
+            Exit::BrokenPipe => ExitCode::from(5),
+            Exit::Timeout => ExitCode::from(6),
+        }
+    }
+}
+
+/// Something that went wrong, which the marquee can't carry on from
+#[derive(Debug)]
+pub enum Error {
+    /// The handler for a signal (the number) couldn't be registered, or the signal couldn't be
+    /// passed on once the marquee had cleaned up
+    #[cfg(unix)]
+    Signal(i32, io::Error),
+    /// Stdin couldn't be read (i.e. a line wasn't valid UTF-8)
+    Input(io::Error),
+    /// The tick file couldn't be read (see `--clock`)
+    Ticks(io::Error),
+    /// A line was longer than `--max-input-len` with `--max-input-policy error`
+    TooLong(usize),
+    /// A line wasn't valid JSON with `--strict`
+    Json(serde_json::Error),
+    /// The output couldn't be written
+    Output(io::Error),
+    /// The input couldn't be passed through (see `--passthrough`)
+    Passthrough(io::Error),
+    /// The recording couldn't be written (see `--record`)
+    Record(io::Error),
+    /// The metrics server couldn't be started (see `--metrics`)
+    Metrics(io::Error),
+    /// The source command couldn't be started (see `--source-cmd`)
+    Source(io::Error),
+    /// The command for the right pane couldn't be started or read (see `--right`)
+    Right(io::Error),
+    /// The prefix or suffix file couldn't be read (see `--prefix-file` and `--suffix-file`)
+    Decoration(io::Error),
+    /// The control FIFO couldn't be opened or read (see `--control-fifo`)
+    ControlFifo(io::Error),
+    /// The filter command couldn't be run (see `--filter-cmd`)
+    Filter(io::Error),
+    /// The frames couldn't be published (see `--publish`)
+    Publish(io::Error),
+    /// The recording couldn't be read, or it wasn't valid (see `marquee replay`)
+    Replay(io::Error),
+    /// The font couldn't be read, or it wasn't valid (see `--figlet`)
+    Font(io::Error),
+    /// The sync group couldn't be joined (see `--sync-group`)
+    Sync(io::Error),
+    /// The export couldn't be written (see `--export-cast` and `marquee export`)
+    Export(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(unix)]
+            Error::Signal(signal, err) => match signal_hook::low_level::signal_name(*signal) {
+                Some(name) => write!(f, "failed to handle {}: {}", name, err),
+                None => write!(f, "failed to handle signal {}: {}", signal, err),
+            },
+            Error::Input(err) => write!(f, "failed to read stdin: {}", err),
+            Error::Ticks(err) => write!(f, "failed to read the tick file: {}", err),
+            Error::TooLong(max) => write!(f, "line is longer than the maximum of {} bytes", max),
+            Error::Json(err) => write!(f, "failed to parse JSON: {}", err),
+            Error::Output(err) => write!(f, "failed to write the output: {}", err),
+            Error::Passthrough(err) => write!(f, "failed to pass the input through: {}", err),
+            Error::Record(err) => write!(f, "failed to write the recording: {}", err),
+            Error::Metrics(err) => write!(f, "failed to start the metrics server: {}", err),
+            Error::Source(err) => write!(f, "failed to start the source command: {}", err),
+            Error::Right(err) => write!(f, "failed to run the right pane command: {}", err),
+            Error::Decoration(err) => write!(f, "failed to read the prefix or suffix: {}", err),
+            Error::ControlFifo(err) => write!(f, "failed to read the control FIFO: {}", err),
+            Error::Filter(err) => write!(f, "failed to run the filter command: {}", err),
+            Error::Publish(err) => write!(f, "failed to publish to the broker: {}", err),
+            Error::Replay(err) => write!(f, "failed to read the recording: {}", err),
+            Error::Font(err) => write!(f, "failed to load the font: {}", err),
+            Error::Sync(err) => write!(f, "failed to join the sync group: {}", err),
+            Error::Export(err) => write!(f, "failed to write the export: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(unix)]
+            Error::Signal(_, err) => Some(err),
+            Error::Input(err)
+            | Error::Ticks(err)
+            | Error::Output(err)
+            | Error::Passthrough(err)
+            | Error::Record(err)
+            | Error::Metrics(err)
+            | Error::Source(err)
+            | Error::Right(err)
+            | Error::Decoration(err)
+            | Error::ControlFifo(err)
+            | Error::Filter(err)
+            | Error::Publish(err)
+            | Error::Replay(err)
+            | Error::Font(err)
+            | Error::Sync(err)
+            | Error::Export(err) => Some(err),
+            Error::Json(err) => Some(err),
+            Error::TooLong(_) => None,
+        }
+    }
+}
+
+impl Error {
+    /// The reason to give for stopping because of this error
+    pub fn exit(&self) -> Exit {
+        match self {
+            Error::Input(err) | Error::Right(err) | Error::Replay(err)
+                if err.kind() == io::ErrorKind::InvalidData =>
+            {
+                Exit::InvalidInput
+            }
+            Error::TooLong(_) | Error::Json(_) => Exit::InvalidInput,
+            Error::Output(err) | Error::Passthrough(err)
+                if err.kind() == io::ErrorKind::BrokenPipe =>
+            {
+                Exit::BrokenPipe
+            }
+            _ => Exit::Io,
+        }
+    }
+}
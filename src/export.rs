@@ -0,0 +1,131 @@
+//! Exporting cycles of the marquee without showing them (see `--export-cast` and `marquee
+//! export`)
+
+use std::{fmt::Write as _, fs, path::Path, sync::mpsc};
+
+use crate::{
+    error::{Error, Exit},
+    figlet::Font,
+    input::{self, Event, ReadOptions},
+    marquee::{Frame, Marquee},
+    text::{strip_escapes, visible_len},
+    Cli,
+};
+
+/// The size of the text in the SVG, in pixels
+const FONT_SIZE: f64 = 14.0;
+/// The height of each row in the SVG
+const ROW_HEIGHT: f64 = 18.0;
+/// The width of each character in the SVG, monospace fonts are usually 0.6 times as wide as they
+/// are tall
+const CHAR_WIDTH: f64 = FONT_SIZE * 0.6;
+/// The space around the content in the SVG
+const PADDING: f64 = 8.0;
+
+/// Read all of stdin, and then work out each frame of `--cycles` cycles of the marquee (or until
+/// it is finished with `--no-loop`)
+pub fn frames(options: &Cli) -> Result<Vec<Frame>, Error> {
+    let (events, rx) = mpsc::channel();
+    input::spawn_stdin(events, ReadOptions::from_options(options));
+
+    let font = Font::from_options(options).map_err(Error::Font)?;
+    let mut marquee = Marquee::new(options.clone(), font);
+    for event in rx {
+        match event {
+            Event::Line(line) => {
+                marquee.push(line, None);
+            }
+            Event::Eof => break,
+            Event::Error(err) => return Err(err),
+            _ => {}
+        }
+    }
+
+    let mut frames = Vec::new();
+    let mut cycles = 0;
+    while cycles < options.cycles && !marquee.is_empty() {
+        let Some(frame) = marquee.frame()? else {
+            continue;
+        };
+        let finished = !options._loop && frame.finished;
+        if frame.cycle_end {
+            cycles += 1;
+        }
+        frames.push(frame);
+        if finished {
+            break;
+        }
+    }
+    Ok(frames)
+}
+
+/// Write `--cycles` cycles of the marquee to an animated SVG at `path`.
+///
+/// The frames are stacked on top of each other, and then moved up one frame every `--delay` (see
+/// `Cli::frame_delay`) so that only one of them is in view at a time.
+pub fn svg(options: &Cli, path: &Path, foreground: &str, background: &str) -> Result<Exit, Error> {
+    let frames = frames(options)?;
+
+    let rows = frames.iter().map(|f| f.rows.len()).max().unwrap_or(1);
+    let chars = frames
+        .iter()
+        .flat_map(|f| &f.rows)
+        .map(|r| visible_len(r))
+        .max()
+        .unwrap_or(0);
+    let frame_height = rows as f64 * ROW_HEIGHT;
+    let content_width = chars as f64 * CHAR_WIDTH;
+    let width = content_width + PADDING * 2.0;
+    let height = frame_height + PADDING * 2.0;
+    let duration = options.frame_delay().as_secs_f64() * frames.len() as f64;
+
+    let mut out = String::new();
+    // Writing to a `String` can't fail
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.1}" height="{height}" viewBox="0 0 {width:.1} {height}">"#,
+    );
+    let _ = writeln!(
+        out,
+        "<style>\
+         text {{ font-family: monospace; font-size: {FONT_SIZE}px; white-space: pre; fill: {fg}; }}\
+         @keyframes marquee {{ to {{ transform: translateY(-{end}px); }} }}\
+         .frames {{ animation: marquee {duration}s steps({steps}) infinite; }}\
+         </style>",
+        fg = escape(foreground),
+        end = frame_height * frames.len() as f64,
+        steps = frames.len().max(1),
+    );
+    let _ = writeln!(
+        out,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        escape(background),
+    );
+    let _ = writeln!(
+        out,
+        r#"<svg x="{PADDING}" y="{PADDING}" width="{content_width:.1}" height="{frame_height}"><g class="frames">"#,
+    );
+    for (i, frame) in frames.iter().enumerate() {
+        for (r, row) in frame.rows.iter().enumerate() {
+            // The baseline of the text, which is a bit above the bottom of the row
+            let y = (i * rows + r) as f64 * ROW_HEIGHT + FONT_SIZE;
+            let _ = writeln!(
+                out,
+                r#"<text y="{y}">{}</text>"#,
+                escape(&strip_escapes(row))
+            );
+        }
+    }
+    out += "</g></svg>\n</svg>\n";
+
+    fs::write(path, out).map_err(Error::Export)?;
+    Ok(Exit::Finished)
+}
+
+/// Escape the characters that can't be put directly into XML text or attributes
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
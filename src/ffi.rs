@@ -0,0 +1,139 @@
+//! A C API for working out the frames of a marquee, this needs the `ffi` feature (see
+//! `include/marquee.h`).
+//!
+//! The library is built with `cargo rustc --release --lib --features ffi --crate-type cdylib`.
+//! Nothing is printed, and the caller has to wait `--delay` between each frame itself.
+
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use clap::Parser;
+
+use crate::{figlet::Font, marquee::Marquee, Cli};
+
+/// A marquee and the last frame that was worked out for it, which is kept so that the pointer
+/// given to the caller stays valid until the next call
+pub struct Handle {
+    marquee: Marquee,
+    frame: CString,
+}
+
+/// Run `f`, returning `failed` instead if it panics, since unwinding into the caller's C code is
+/// undefined behaviour
+fn catch<T>(failed: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| {
+        log::error!("The marquee panicked");
+        failed
+    })
+}
+
+/// Create a marquee from `argc` command line arguments (without the name of the program), which
+/// are the same as the ones that the `marquee` command takes.
+///
+/// Returns null if the arguments aren't valid, the font couldn't be loaded, or it panicked.
+///
+/// # Safety
+///
+/// `argv` has to point to `argc` valid NUL-terminated strings (it may be null if `argc` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn marquee_new(argv: *const *const c_char, argc: usize) -> *mut Handle {
+    catch(ptr::null_mut(), || {
+        let args: &[*const c_char] = if argc == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(argv, argc)
+        };
+        let args = args
+            .iter()
+            .map(|&arg| CStr::from_ptr(arg).to_string_lossy().into_owned());
+        let options =
+            match Cli::try_parse_from(std::iter::once(String::from("marquee")).chain(args)) {
+                Ok(options) => options,
+                Err(err) => {
+                    log::warn!("Invalid arguments: {}", err);
+                    return ptr::null_mut();
+                }
+            };
+        let font = match Font::from_options(&options) {
+            Ok(font) => font,
+            Err(err) => {
+                log::warn!("Failed to load the font: {}", err);
+                return ptr::null_mut();
+            }
+        };
+
+        Box::into_raw(Box::new(Handle {
+            marquee: Marquee::new(options, font),
+            frame: CString::default(),
+        }))
+    })
+}
+
+/// Show `text` on the marquee, this is the same as a line of input to the `marquee` command (so
+/// it is JSON with `--json`, and an empty string clears it).
+///
+/// Returns 0 if the text was shown, or -1 if `handle` or `text` is null or it panicked.
+///
+/// # Safety
+///
+/// `handle` has to come from `marquee_new`, and `text` has to be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn marquee_set_text(handle: *mut Handle, text: *const c_char) -> c_int {
+    catch(-1, || {
+        let Some(handle) = handle.as_mut() else {
+            return -1;
+        };
+        if text.is_null() {
+            return -1;
+        }
+        let text = CStr::from_ptr(text).to_string_lossy().into_owned();
+        handle.marquee.push(text, None);
+        0
+    })
+}
+
+/// Work out the next frame, the rows are joined with `\n`.
+///
+/// Returns null if there is nothing to show (or it panicked).  The string belongs to the marquee,
+/// and is valid until the next call to `marquee_next_frame` or `marquee_free`.
+///
+/// # Safety
+///
+/// `handle` has to come from `marquee_new`.
+#[no_mangle]
+pub unsafe extern "C" fn marquee_next_frame(handle: *mut Handle) -> *const c_char {
+    catch(ptr::null(), || {
+        let Some(handle) = handle.as_mut() else {
+            return ptr::null();
+        };
+        let frame = match handle.marquee.frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return ptr::null(),
+            Err(err) => {
+                log::warn!("Failed to work out the frame: {}", err);
+                return ptr::null();
+            }
+        };
+        // A NUL in the content would end the string early, so they're removed
+        let rows = frame.rows.join("\n").replace('\0', "");
+        handle.frame = CString::new(rows).unwrap_or_default();
+        handle.frame.as_ptr()
+    })
+}
+
+/// Free a marquee that was created with `marquee_new`.
+///
+/// # Safety
+///
+/// `handle` has to come from `marquee_new` (or be null), and can't be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn marquee_free(handle: *mut Handle) {
+    catch((), || {
+        if !handle.is_null() {
+            drop(Box::from_raw(handle));
+        }
+    })
+}
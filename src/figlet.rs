@@ -0,0 +1,185 @@
+//! Drawing the content in large letters using a FIGlet font (see `--figlet`)
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use crate::Cli;
+
+/// The fonts that are built in, which can be used by name rather than by path
+const BUILT_IN: &[(&str, &str)] = &[("block", include_str!("../fonts/block.flf"))];
+
+/// A FIGlet font, each character is drawn over `height` rows
+#[derive(Debug, Clone)]
+pub struct Font {
+    height: usize,
+    /// The rows of each character, every row of a character is the same width
+    glyphs: HashMap<char, Vec<String>>,
+}
+
+impl Font {
+    /// Load the font that the content should be drawn with, if any (see `--figlet` and
+    /// `--braille`)
+    pub fn from_options(options: &Cli) -> io::Result<Option<Self>> {
+        let name = match (&options.figlet, options.braille) {
+            (Some(name), _) => name,
+            (None, true) => "block",
+            (None, false) => return Ok(None),
+        };
+        Self::load(name).map(Some)
+    }
+
+    /// Load one of the built-in fonts (i.e. `block`), or the `.flf` file at `name`
+    pub fn load(name: &str) -> io::Result<Self> {
+        match BUILT_IN.iter().find(|(n, _)| *n == name) {
+            Some((_, font)) => Self::parse(font),
+            None => Self::parse(&fs::read_to_string(Path::new(name))?),
+        }
+    }
+
+    /// Parse a font in the FIGlet (`.flf`) format.
+    ///
+    /// Characters are always drawn next to each other, the layout (smushing) settings of the font
+    /// are ignored.
+    fn parse(font: &str) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        let mut lines = font.lines();
+        let header = lines.next().ok_or_else(|| invalid("the font is empty"))?;
+        let mut fields = header
+            .strip_prefix("flf2a")
+            .ok_or_else(|| invalid("the font isn't a FIGlet font"))?
+            .chars();
+        let hard_blank = fields
+            .next()
+            .ok_or_else(|| invalid("the font header is missing the hard blank"))?;
+        let fields: Vec<&str> = fields.as_str().split_whitespace().collect();
+        let field = |i: usize| -> io::Result<usize> {
+            fields
+                .get(i)
+                .and_then(|f| f.parse().ok())
+                .ok_or_else(|| invalid("the font header is invalid"))
+        };
+        let height = field(0)?;
+        let comments = field(4)?;
+        if height == 0 {
+            return Err(invalid("the font has a height of 0"));
+        }
+
+        let mut lines = lines.skip(comments);
+        // Every font has the printable ASCII characters and then the German ones in order,
+        // anything after that starts with the code of the character
+        let mut glyphs = HashMap::new();
+        let required = (32..=126u32).chain([196, 214, 220, 228, 246, 252, 223]);
+        for code in required {
+            match read_glyph(&mut lines, height, hard_blank) {
+                Some(rows) => {
+                    glyphs.insert(char::from_u32(code).expect("the code is valid"), rows);
+                }
+                // Some fonts leave out the German characters
+                None if code > 126 => break,
+                None => return Err(invalid("the font is missing some characters")),
+            }
+        }
+        while let Some(line) = lines.next() {
+            let code = line.split_whitespace().next().unwrap_or_default();
+            let code = if let Some(hex) = code.strip_prefix("0x").or(code.strip_prefix("0X")) {
+                u32::from_str_radix(hex, 16).ok()
+            } else if code.len() > 1 && code.starts_with('0') {
+                u32::from_str_radix(&code[1..], 8).ok()
+            } else {
+                code.parse().ok()
+            };
+            let Some(rows) = read_glyph(&mut lines, height, hard_blank) else {
+                break;
+            };
+            // Negative codes aren't characters, so they can't be in the content
+            if let Some(c) = code.and_then(char::from_u32) {
+                glyphs.insert(c, rows);
+            }
+        }
+
+        Ok(Self { height, glyphs })
+    }
+
+    /// The rows of `c`, or of `?` if the font doesn't have it
+    fn glyph(&self, c: char) -> &[String] {
+        self.glyphs
+            .get(&c)
+            .or_else(|| self.glyphs.get(&'?'))
+            .map_or(&[], |g| g)
+    }
+
+    /// The width of `c` when it is drawn
+    fn glyph_width(&self, c: char) -> usize {
+        self.glyph(c).first().map_or(0, |r| r.chars().count())
+    }
+
+    /// The width of `s` when it is drawn
+    pub fn width(&self, s: &str) -> usize {
+        s.chars().map(|c| self.glyph_width(c)).sum()
+    }
+
+    /// Draw `s`, returning each row and the column that each character starts at (along with the
+    /// width at the end)
+    pub fn render(&self, s: &str) -> (Vec<String>, Vec<usize>) {
+        let mut rows = vec![String::new(); self.height];
+        let mut columns = vec![0];
+        for c in s.chars() {
+            let glyph = self.glyph(c);
+            for (row, g) in rows.iter_mut().zip(glyph) {
+                *row += g;
+            }
+            columns.push(columns.last().expect("there is always a column") + self.glyph_width(c));
+        }
+        (rows, columns)
+    }
+}
+
+/// Read the rows of a single character from a FIGlet font, padding them to the same width
+fn read_glyph<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    height: usize,
+    hard_blank: char,
+) -> Option<Vec<String>> {
+    let mut rows = Vec::with_capacity(height);
+    for _ in 0..height {
+        let line = lines.next()?.trim_end_matches('\r');
+        // Each row ends with an end mark, which is doubled on the last row
+        let end = line.chars().last()?;
+        rows.push(line.trim_end_matches(end).replace(hard_blank, " "));
+    }
+    let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+    for row in &mut rows {
+        let len = row.chars().count();
+        row.extend(std::iter::repeat_n(' ', width - len));
+    }
+    Some(rows)
+}
+
+/// Turn `rows` into Braille characters, where every character other than a space is a dot.
+///
+/// Each Braille character holds 2 columns and 4 rows of dots, so there are half as many columns
+/// and a quarter as many rows.
+pub fn braille(rows: &[String]) -> Vec<String> {
+    // The bit for each dot of a Braille character, by row and then column
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+    let rows: Vec<Vec<char>> = rows.iter().map(|r| r.chars().collect()).collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    rows.chunks(DOTS.len())
+        .map(|chunk| {
+            (0..columns.div_ceil(2))
+                .map(|cell| {
+                    let mut bits = 0;
+                    for (row, dots) in chunk.iter().zip(DOTS) {
+                        for (x, dot) in dots.into_iter().enumerate() {
+                            if row.get(cell * 2 + x).is_some_and(|&c| c != ' ') {
+                                bits |= dot;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + bits).expect("every Braille pattern is a character")
+                })
+                .collect()
+        })
+        .collect()
+}
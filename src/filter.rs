@@ -0,0 +1,87 @@
+//! Passing the messages or frames through another command before they are shown (see
+//! `--filter-cmd`)
+
+use std::{
+    io::{self, Read, Write},
+    process::{Child, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::source::shell;
+
+/// How long the command has to finish, after which it is killed and the input is shown as it is.
+/// It is run in between frames, so a command that hangs would otherwise stop the marquee.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Run `cmd` with `input` on its stdin, and return what it printed without the last line ending.
+///
+/// Returns `None` if the command failed or took longer than `TIMEOUT`, so that the input can be
+/// shown as it is.
+pub fn filter(cmd: &str, input: &str) -> io::Result<Option<String>> {
+    let deadline = Instant::now() + TIMEOUT;
+    let mut child = shell(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    // The input is written on another thread, since the command may not read all of it before it
+    // starts printing (which would block both of them once the pipes are full).  The output is
+    // read on another thread as well, so that the command can be given up on.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = format!("{}\n", input);
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut out = Vec::new();
+        let _ = tx.send(stdout.read_to_end(&mut out).map(|_| out));
+    });
+
+    let Ok(out) = rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) else {
+        return give_up(child);
+    };
+    let out = out?;
+    // Stdout is closed, so the command has (almost always) exited
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            return give_up(child);
+        }
+        thread::sleep(Duration::from_millis(1));
+    };
+    if let Ok(Err(err)) = writer.join() {
+        // The command doesn't have to read its input
+        if err.kind() != io::ErrorKind::BrokenPipe {
+            return Err(err);
+        }
+    }
+
+    if !status.success() {
+        log::warn!("The filter command failed ({})", status);
+        return Ok(None);
+    }
+    let mut out =
+        String::from_utf8(out).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if out.ends_with('\n') {
+        out.pop();
+        if out.ends_with('\r') {
+            out.pop();
+        }
+    }
+    Ok(Some(out))
+}
+
+/// Kill the command once it has taken longer than `TIMEOUT`
+fn give_up(mut child: Child) -> io::Result<Option<String>> {
+    log::warn!(
+        "The filter command took longer than {:?}, so it was stopped",
+        TIMEOUT
+    );
+    let _ = child.kill();
+    child.wait()?;
+    Ok(None)
+}
@@ -0,0 +1,243 @@
+//! Showing the frames on hardware displays (see `--output`), this needs the `hardware` feature
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    os::fd::AsRawFd,
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use crate::{marquee::Frame, output::Sink, text::strip_escapes};
+
+/// The `ioctl` that sets the address of the device that an I2C bus talks to (from
+/// `linux/i2c-dev.h`)
+const I2C_SLAVE: u64 = 0x0703;
+
+/// An HD44780 character LCD, driven through a PCF8574 I2C backpack in 4-bit mode.
+///
+/// The pins of the backpack are wired to `RS`, `RW`, `E`, the backlight, and then `D4`-`D7` of
+/// the LCD, which is how almost all of them are sold.
+#[derive(Debug)]
+pub struct Lcd {
+    bus: File,
+    /// The number of characters written to each row last time, so anything left over can be
+    /// cleared
+    prev: Vec<usize>,
+}
+
+impl Lcd {
+    /// Register select, set when sending a character rather than a command
+    const RS: u8 = 0x01;
+    /// Enable, the LCD reads the data when this goes low
+    const EN: u8 = 0x04;
+    const BACKLIGHT: u8 = 0x08;
+    /// The address of the start of each row
+    const ROWS: [u8; 4] = [0x00, 0x40, 0x14, 0x54];
+
+    pub fn open(path: &Path, address: u16) -> io::Result<Self> {
+        let bus = OpenOptions::new().read(true).write(true).open(path)?;
+        // SAFETY: The file descriptor is open for the whole call, and `I2C_SLAVE` only takes the
+        // address as an integer
+        if unsafe { libc::ioctl(bus.as_raw_fd(), I2C_SLAVE as _, address as libc::c_ulong) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut lcd = Self {
+            bus,
+            prev: Vec::new(),
+        };
+        // The LCD starts in 8-bit mode, and it may be half way through a byte, so it has to be
+        // put into 8-bit mode three times before it can be switched to 4-bit mode
+        thread::sleep(Duration::from_millis(50));
+        for _ in 0..3 {
+            lcd.write_nibble(0x30, 0)?;
+            thread::sleep(Duration::from_millis(5));
+        }
+        lcd.write_nibble(0x20, 0)?;
+        // Two lines with 5x8 characters, the display on without a cursor, cleared, and moving to
+        // the right after each character
+        lcd.command(0x28)?;
+        lcd.command(0x0c)?;
+        lcd.command(0x01)?;
+        thread::sleep(Duration::from_millis(2));
+        lcd.command(0x06)?;
+        Ok(lcd)
+    }
+
+    /// Send the top 4 bits of `nibble`, pulsing enable so that the LCD reads them
+    fn write_nibble(&mut self, nibble: u8, mode: u8) -> io::Result<()> {
+        let data = (nibble & 0xf0) | mode | Self::BACKLIGHT;
+        self.bus.write_all(&[data | Self::EN, data])
+    }
+
+    fn send(&mut self, byte: u8, mode: u8) -> io::Result<()> {
+        self.write_nibble(byte, mode)?;
+        self.write_nibble(byte << 4, mode)
+    }
+
+    fn command(&mut self, command: u8) -> io::Result<()> {
+        self.send(command, 0)
+    }
+}
+
+impl Sink for Lcd {
+    fn print(&mut self, frame: &Frame) -> io::Result<()> {
+        let rows = frame.rows.len().max(self.prev.len()).min(Self::ROWS.len());
+        self.prev.resize(rows, 0);
+        for r in 0..rows {
+            // The LCD only has ASCII (and some Japanese) characters
+            let row: Vec<u8> = frame
+                .rows
+                .get(r)
+                .map(|row| strip_escapes(row))
+                .unwrap_or_default()
+                .chars()
+                .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+                .collect();
+
+            self.command(0x80 | Self::ROWS[r])?;
+            for &c in &row {
+                self.send(c, Self::RS)?;
+            }
+            for _ in row.len()..self.prev[r] {
+                self.send(b' ', Self::RS)?;
+            }
+            self.prev[r] = row.len();
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        // Leave the last frame on the display
+        Ok(())
+    }
+}
+
+/// A chain of MAX7219 8x8 LED matrices, driven through spidev.
+///
+/// Each character of the frame other than a space is a single LED (i.e. with `--figlet`), and
+/// the first module in the chain is on the left.
+#[derive(Debug)]
+pub struct Matrix {
+    spi: File,
+    modules: usize,
+}
+
+impl Matrix {
+    pub fn open(path: &Path, modules: usize) -> io::Result<Self> {
+        let mut matrix = Self {
+            spi: OpenOptions::new().write(true).open(path)?,
+            modules,
+        };
+        // No test mode, no decoding of digits, all 8 rows, low brightness, and then turn it on
+        for (register, data) in [(0x0f, 0), (0x09, 0), (0x0b, 7), (0x0a, 3), (0x0c, 1)] {
+            matrix.write(register, |_| data)?;
+        }
+        Ok(matrix)
+    }
+
+    /// Set `register` of every module, `data` is given the index of the module.  The data for
+    /// the last module is sent first, since it is shifted along the chain.
+    fn write(&mut self, register: u8, data: impl Fn(usize) -> u8) -> io::Result<()> {
+        let buf: Vec<u8> = (0..self.modules)
+            .rev()
+            .flat_map(|m| [register, data(m)])
+            .collect();
+        self.spi.write_all(&buf)
+    }
+}
+
+impl Sink for Matrix {
+    fn print(&mut self, frame: &Frame) -> io::Result<()> {
+        let rows: Vec<Vec<bool>> = frame
+            .rows
+            .iter()
+            .map(|row| strip_escapes(row).chars().map(|c| c != ' ').collect())
+            .collect();
+        for y in 0..8 {
+            let row = rows.get(y).map(Vec::as_slice).unwrap_or_default();
+            self.write(y as u8 + 1, |m| {
+                (0..8).fold(0, |byte, x| {
+                    let on = row.get(m * 8 + x).copied().unwrap_or(false);
+                    byte | (u8::from(on) << (7 - x))
+                })
+            })?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A serial device (i.e. a USB adapter wired to the controller of an LED sign), which is sent the
+/// prefix, and then each row of the frame followed by the terminator
+#[derive(Debug)]
+pub struct Serial {
+    port: File,
+    prefix: Vec<u8>,
+    terminator: Vec<u8>,
+}
+
+impl Serial {
+    pub fn open(path: &Path, baud: u32, prefix: Vec<u8>, terminator: Vec<u8>) -> io::Result<Self> {
+        let speed = match baud {
+            1200 => libc::B1200,
+            2400 => libc::B2400,
+            4800 => libc::B4800,
+            9600 => libc::B9600,
+            19200 => libc::B19200,
+            38400 => libc::B38400,
+            57600 => libc::B57600,
+            115200 => libc::B115200,
+            230400 => libc::B230400,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported baud rate: {}", baud),
+                ))
+            }
+        };
+        let port = OpenOptions::new().read(true).write(true).open(path)?;
+
+        // Raw mode, so that nothing is changed on the way out (i.e. `\n` to `\r\n`)
+        // SAFETY: `termios` is plain data that is filled in by `tcgetattr`, and the file
+        // descriptor is open for all of the calls
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(port.as_raw_fd(), &mut termios) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            libc::cfmakeraw(&mut termios);
+            if libc::cfsetspeed(&mut termios, speed) < 0
+                || libc::tcsetattr(port.as_raw_fd(), libc::TCSANOW, &termios) < 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(Self {
+            port,
+            prefix,
+            terminator,
+        })
+    }
+}
+
+impl Sink for Serial {
+    fn print(&mut self, frame: &Frame) -> io::Result<()> {
+        let mut buf = self.prefix.clone();
+        for row in &frame.rows {
+            buf.extend_from_slice(strip_escapes(row).as_bytes());
+            buf.extend_from_slice(&self.terminator);
+        }
+        self.port.write_all(&buf)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
@@ -0,0 +1,76 @@
+//! Running commands when something happens to the marquee (see `--on-change` and `--on-cycle`)
+
+use std::{
+    io::Write,
+    process::{Child, Stdio},
+    rc::Rc,
+    thread,
+};
+
+use crate::{marquee::content_of, source, Cli};
+
+/// Runs the `--on-change` command each time a new message is shown
+#[derive(Debug)]
+pub struct OnChange {
+    cmd: String,
+    /// The message that the command was run for last, so that it is only run when it changes
+    message: Option<Rc<str>>,
+}
+
+impl OnChange {
+    pub fn new(cmd: String) -> Self {
+        Self { cmd, message: None }
+    }
+
+    /// Run the command for `message` if it isn't the same as the last one, with the content in
+    /// `MARQUEE_MESSAGE` and the whole line (i.e. the JSON) on its stdin
+    pub fn run(&mut self, options: &Cli, message: &Rc<str>) {
+        if self
+            .message
+            .as_ref()
+            .is_some_and(|m| Rc::ptr_eq(m, message))
+        {
+            return;
+        }
+        self.message = Some(Rc::clone(message));
+
+        let child = source::shell(&self.cmd)
+            .env("MARQUEE_MESSAGE", content_of(options, message))
+            .stdin(Stdio::piped())
+            .spawn();
+        match child {
+            Ok(child) => wait(child, Some(message.to_string())),
+            Err(err) => log::warn!("Failed to run the --on-change command: {}", err),
+        }
+    }
+}
+
+/// Run the `--on-cycle` command, with the content of the message that has just been scrolled
+/// through in `MARQUEE_MESSAGE`
+pub fn on_cycle(options: &Cli, cmd: &str, message: &str) {
+    let child = source::shell(cmd)
+        .env("MARQUEE_MESSAGE", content_of(options, message))
+        .stdin(Stdio::null())
+        .spawn();
+    match child {
+        Ok(child) => wait(child, None),
+        Err(err) => log::warn!("Failed to run the --on-cycle command: {}", err),
+    }
+}
+
+/// Write `input` to the stdin of `child` and wait for it on another thread, so that it doesn't
+/// hold up the frames or stay around as a zombie
+fn wait(mut child: Child, input: Option<String>) {
+    let stdin = child.stdin.take();
+    thread::spawn(move || {
+        if let (Some(mut stdin), Some(input)) = (stdin, input) {
+            // The command doesn't have to read it
+            let _ = writeln!(stdin, "{}", input);
+        }
+        match child.wait() {
+            Ok(status) if !status.success() => log::warn!("A hook command failed ({})", status),
+            Ok(_) => {}
+            Err(err) => log::warn!("Failed to wait for a hook command: {}", err),
+        }
+    });
+}
@@ -0,0 +1,311 @@
+//! Reading the input that is shown in the marquee
+
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use encoding_rs::{Encoding, UTF_8};
+
+use crate::{
+    control::{self, Action},
+    error::Error,
+    Cli, InvalidUtf8, MaxInputPolicy,
+};
+
+/// Something that happened to one of the inputs, these are handled by the event loop in `main`
+#[derive(Debug)]
+pub enum Event {
+    /// A line was read
+    Line(String),
+    /// A line was read for the right pane (`--right`)
+    RightLine(String),
+    /// The prefix file has changed (`--prefix-file`)
+    Prefix(String),
+    /// The suffix file has changed (`--suffix-file`)
+    Suffix(String),
+    /// A control command was read, either from the input or from `--control-fifo`
+    Control(Result<Action, String>),
+    /// The input has been closed
+    Eof,
+    /// The source command has closed its output, and it should be started again
+    /// (`--restart-source`)
+    SourceExited,
+    /// It is time to start the source command again
+    RestartSource,
+    /// A line was read from the tick file (`--clock external`)
+    Tick,
+    /// The tick file has been closed
+    TicksClosed,
+    /// The input couldn't be read, so the marquee should stop
+    Error(Error),
+    /// A signal was received (see `spawn_signals`)
+    #[cfg(unix)]
+    Signal(i32),
+}
+
+/// How the lines of input are read
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOptions {
+    /// The most bytes of a line that are stored (`--max-input-len`)
+    pub max_len: Option<usize>,
+    /// What to do with lines that are longer than that
+    pub policy: MaxInputPolicy,
+    /// If a carriage return ends a line as well as a newline (`--split-cr`)
+    pub split_cr: bool,
+    /// What the input is decoded from, when it isn't UTF-8 (`--encoding`)
+    pub encoding: Option<&'static Encoding>,
+    /// What to do with a line that isn't valid UTF-8 (`--invalid-utf8`)
+    pub invalid_utf8: InvalidUtf8,
+}
+
+impl ReadOptions {
+    pub fn from_options(options: &Cli) -> Self {
+        Self {
+            max_len: options.max_input_len,
+            policy: options.max_input_policy,
+            split_cr: options.split_cr,
+            encoding: options.encoding.filter(|&e| e != UTF_8),
+            invalid_utf8: options.invalid_utf8,
+        }
+    }
+}
+
+/// Read a single line from `reader` (without the line ending), storing at most `max_len` bytes of
+/// it.
+///
+/// Returns `None` once the end of the input is reached, otherwise the line and whether it was cut
+/// short.  Lines that aren't valid UTF-8 are left out with `--invalid-utf8 skip`.
+pub fn read_line(
+    reader: &mut impl BufRead,
+    read: ReadOptions,
+) -> io::Result<Option<(String, bool)>> {
+    loop {
+        match read_any_line(reader, read) {
+            Err(err)
+                if err.kind() == io::ErrorKind::InvalidData
+                    && read.invalid_utf8 == InvalidUtf8::Skip =>
+            {
+                log::warn!("Skipping a line that isn't valid UTF-8: {}", err);
+            }
+            line => return line,
+        }
+    }
+}
+
+/// Read a single line from `reader`, see `read_line`
+fn read_any_line(
+    reader: &mut impl BufRead,
+    read: ReadOptions,
+) -> io::Result<Option<(String, bool)>> {
+    let max = read.max_len;
+    let mut buf = Vec::new();
+    let mut truncated = false;
+    let mut read_any = false;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            if !read_any {
+                return Ok(None);
+            }
+            break;
+        }
+        read_any = true;
+
+        let newline = available
+            .iter()
+            .position(|&b| b == b'\n' || (read.split_cr && b == b'\r'));
+        let chunk = &available[..newline.unwrap_or(available.len())];
+        let room = max.map_or(chunk.len(), |max| max.saturating_sub(buf.len()));
+        if chunk.len() > room {
+            truncated = true;
+        }
+        buf.extend_from_slice(&chunk[..chunk.len().min(room)]);
+
+        let used = newline.map_or(available.len(), |n| n + 1);
+        reader.consume(used);
+        if newline.is_some() {
+            break;
+        }
+    }
+
+    if buf.last() == Some(&b'\r') && !truncated {
+        buf.pop();
+    }
+
+    if let Some(encoding) = read.encoding {
+        let (line, _) = encoding.decode_without_bom_handling(&buf);
+        return Ok(Some((line.into_owned(), truncated)));
+    }
+
+    let line = match String::from_utf8(buf) {
+        Ok(line) => line,
+        // If the line was cut off in the middle of a character, then remove that character
+        Err(e) if truncated && e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut buf = e.into_bytes();
+            buf.truncate(valid);
+            String::from_utf8(buf).expect("only valid UTF-8 is left")
+        }
+        Err(e) if read.invalid_utf8 == InvalidUtf8::Replace => {
+            String::from_utf8_lossy(e.as_bytes()).into_owned()
+        }
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    };
+    Ok(Some((line, truncated)))
+}
+
+/// Start the thread that reads each line of stdin and sends it to `events`.
+///
+/// Reading stdin blocks, so this is the only part that doesn't run in the event loop.  The thread
+/// is never joined, since stdin may never be closed, it is stopped when `main` returns.
+pub fn spawn_stdin(events: Sender<Event>, read: ReadOptions) {
+    thread::spawn(move || {
+        if read_lines(&events, io::stdin().lock(), read) {
+            let _ = events.send(Event::Eof);
+        }
+    });
+}
+
+/// Send each line of `reader` to `events`.
+///
+/// Returns true once the end of `reader` is reached, or false if it stopped before then (because
+/// it couldn't be read, or the event loop has stopped).
+pub fn read_lines(events: &Sender<Event>, mut reader: impl BufRead, read: ReadOptions) -> bool {
+    loop {
+        let (line, truncated) = match read_line(&mut reader, read) {
+            Ok(Some(line)) => line,
+            Ok(None) => return true,
+            Err(err) => {
+                let _ = events.send(Event::Error(Error::Input(err)));
+                return false;
+            }
+        };
+
+        // `\r\n` (and a line that starts with `\r`) would otherwise be followed by an empty line,
+        // which clears the marquee
+        if read.split_cr && line.is_empty() {
+            continue;
+        }
+
+        if truncated {
+            match read.policy {
+                MaxInputPolicy::Truncate => {}
+                MaxInputPolicy::Reject => continue,
+                MaxInputPolicy::Error => {
+                    let max = read
+                        .max_len
+                        .expect("lines are only truncated with a maximum");
+                    let _ = events.send(Event::Error(Error::TooLong(max)));
+                    return false;
+                }
+            }
+        }
+
+        if events.send(Event::Line(line)).is_err() {
+            // The event loop has stopped, so nobody cares about the rest of the input
+            return false;
+        }
+    }
+}
+
+/// Read a file that holds a prefix or suffix (see `--prefix-file`), without the line ending
+pub fn read_decoration(path: &Path) -> io::Result<String> {
+    let text = fs::read_to_string(path)?;
+    Ok(text.trim_end_matches(['\n', '\r']).to_string())
+}
+
+/// Start the thread that checks `path` for changes every half a second, and sends what is in it
+/// to `events` (using `event`) when it does.
+///
+/// The file is expected to have been read already, so it is only sent once it changes.
+pub fn spawn_watch(events: Sender<Event>, path: PathBuf, event: fn(String) -> Event) {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last: Option<SystemTime> = modified(&path);
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(500));
+        let now = modified(&path);
+        if now.is_none() || now == last {
+            // The file may be missing for a moment while it is being replaced
+            continue;
+        }
+        last = now;
+        match read_decoration(&path) {
+            Ok(text) => {
+                log::info!("{} has changed: {:?}", path.display(), text);
+                if events.send(event(text)).is_err() {
+                    return;
+                }
+            }
+            Err(err) => log::warn!("Couldn't read {}: {}", path.display(), err),
+        }
+    });
+}
+
+/// Start the thread that sends each command that is written to the control FIFO to `events` (see
+/// `--control-fifo`).
+///
+/// The FIFO is opened for writing as well, so that it doesn't reach the end each time a writer
+/// closes it.
+pub fn spawn_control(events: Sender<Event>, path: &Path) -> io::Result<()> {
+    let fifo = File::options().read(true).write(true).open(path)?;
+    thread::spawn(move || {
+        for line in BufReader::new(fifo).lines() {
+            let event = match line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => Event::Control(control::from_fifo(&line)),
+                Err(err) => Event::Error(Error::ControlFifo(err)),
+            };
+            if events.send(event).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Start the thread that sends a tick to `events` for each line of `file` (see `--clock`)
+pub fn spawn_ticks(events: Sender<Event>, file: File) {
+    thread::spawn(move || {
+        for line in BufReader::new(file).lines() {
+            let event = match line {
+                Ok(_) => Event::Tick,
+                Err(err) => Event::Error(Error::Ticks(err)),
+            };
+            if events.send(event).is_err() {
+                return;
+            }
+        }
+
+        let _ = events.send(Event::TicksClosed);
+    });
+}
+
+/// Start the thread that sends each of `signals` to `events` when it is received.
+///
+/// Unlike setting a flag, this wakes up the event loop, so the signal is handled straight away
+/// even when it is waiting for input.
+#[cfg(unix)]
+pub fn spawn_signals(events: Sender<Event>, signals: &[i32]) -> Result<(), Error> {
+    // Each signal is registered on its own, so that the error says which one couldn't be handled
+    let (&first, rest) = signals.split_first().expect("there is at least one signal");
+    let mut signals =
+        signal_hook::iterator::Signals::new([first]).map_err(|err| Error::Signal(first, err))?;
+    for &signal in rest {
+        signals
+            .add_signal(signal)
+            .map_err(|err| Error::Signal(signal, err))?;
+    }
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            if events.send(Event::Signal(signal)).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,1735 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use encoding_rs::Encoding;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+mod bench;
+mod cast;
+mod control;
+mod error;
+mod export;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod figlet;
+mod filter;
+#[cfg(feature = "hardware")]
+mod hardware;
+mod hook;
+mod input;
+mod marquee;
+mod metrics;
+mod notify;
+mod output;
+mod pane;
+mod publish;
+#[cfg(feature = "python")]
+mod python;
+mod record;
+mod render;
+mod replay;
+mod source;
+mod stats;
+mod status_line;
+mod style;
+mod sync;
+mod text;
+#[cfg(target_arch = "wasm32")]
+mod wasm;
+mod width;
+
+#[cfg(feature = "bench")]
+pub use bench::CountingAlloc;
+use control::Action;
+pub use error::{Error, Exit};
+use figlet::Font;
+use hook::OnChange;
+use input::{Event, ReadOptions};
+use marquee::{Frame, Marquee};
+use notify::Notifier;
+use output::Target;
+use pane::RightPane;
+use publish::{Broker, Publisher};
+use record::Recorder;
+pub use render::{render_frame, Config};
+use source::{Control, Source};
+use stats::Stats;
+use style::Highlight;
+use sync::SyncClock;
+use text::Replace;
+use width::Widths;
+
+/// Read stdin and output it in a marquee style
+///
+/// Once a line is read into stdin, it will stop the previous marquee and start the new one from
+/// the beginning.
+///
+/// If an empty string is passed, then nothing is returned and it will keep waiting for more input.
+///
+/// This is intended for use with user-facing output, however, if one wants it to be used in a
+/// pipeline of some sort, I'd recommend using `marquee -ld0`
+///
+/// See https://crates.io/crates/marquee for usage examples.
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Log what the marquee is doing to stderr, this can be passed multiple times to log more.
+    ///
+    /// The logs can also be filtered using the `RUST_LOG` environment variable (i.e.
+    /// `RUST_LOG=debug`).
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Milliseconds to delay between every print
+    #[arg(short, long, value_name = "ms", default_value_t = 1000)]
+    delay: u64,
+
+    /// Show each frame up to this much earlier or later than the delay, which is either
+    /// milliseconds or a percentage of the delay (i.e. `50` or `10%`), so that several marquees
+    /// don't move in lockstep.
+    ///
+    /// Note: This isn't used with `--sync-group` or `--clock external`
+    #[arg(long, value_name = "ms|%")]
+    jitter: Option<Jitter>,
+
+    /// What to do when a frame is shown late (i.e. the terminal was blocked for longer than the
+    /// delay).
+    ///
+    /// `skip` moves the content on by the frames that were missed without showing them and `burst`
+    /// shows the missed frames straight away, so that the content moves at the same speed
+    /// overall.  `none` just carries on from where it was.
+    #[arg(short, long, value_name = "policy", default_value = "none")]
+    catch_up: CatchUp,
+
+    /// What decides when each frame is shown.
+    ///
+    /// `external` shows a frame for each line read from `--tick-file` instead of waiting for
+    /// `--delay`, which makes the output the same every time (i.e. for testing).  It exits once
+    /// the tick file is closed and every tick has been shown.
+    #[arg(long, value_name = "clock", default_value = "wall")]
+    clock: Clock,
+
+    /// Line up the frames with every other marquee in the same group (i.e. one on each monitor),
+    /// so that the same content is always in the same place.
+    ///
+    /// The group is shared through a file in the temporary directory, and every marquee in it
+    /// should use the same `--delay`.
+    #[arg(long, value_name = "name", conflicts_with = "tick_file")]
+    sync_group: Option<String>,
+
+    /// The file (or FIFO) to read ticks from, each line is a single frame (i.e. `/dev/fd/3`)
+    ///
+    /// Note: This is only used when `clock` is `external`
+    #[arg(long, value_name = "path", required_if_eq("clock", "external"))]
+    tick_file: Option<PathBuf>,
+
+    /// The maximum width of each output line.
+    ///
+    /// If the length of the input < width, then it will just print the input.
+    ///
+    /// Note: This *only* impacts the moving content, the prefix/suffix is not included
+    #[arg(short, long, value_name = "chars", default_value_t = 20)]
+    width: usize,
+
+    /// How content that is wider than the output is shown.
+    ///
+    /// `truncate` cuts the content off at the end of the output (ending it with `--ellipsis`)
+    /// rather than moving it.
+    #[arg(short, long, value_name = "mode", default_value = "scroll")]
+    mode: Mode,
+
+    /// When to move the content, `auto` only does this when the output is a terminal, or with
+    /// `--no-loop` or `--delay 0` (i.e. `marquee -ld0`, which sends each frame to a pipeline).
+    ///
+    /// Otherwise each message is shown once with `--mode truncate`, so that a file (or another
+    /// program) isn't sent thousands of frames.
+    ///
+    /// Note: This is only used when `--output` is `stdout`
+    #[arg(long, value_name = "when", default_value = "auto")]
+    animate: When,
+
+    /// The text to put at the end of content that has been cut off when using `--mode truncate`
+    #[arg(long, value_name = "text", default_value_t = String::from("…"))]
+    ellipsis: String,
+
+    /// Pad content that fits in the output so that it is always `width` characters wide, with the
+    /// content aligned to the left, center, or right.
+    ///
+    /// This keeps the output the same length whether or not the content is moving.
+    #[arg(short = 'A', long, value_name = "align")]
+    align: Option<Align>,
+
+    /// The character to pad content with when using `--align`.
+    ///
+    /// If this is set without `--align`, the content is aligned to the left.
+    #[arg(short = 'P', long, value_name = "char")]
+    pad_char: Option<char>,
+
+    /// The total width of each output line, including the prefix and suffix.
+    ///
+    /// This overrides `--width`, which is worked out from the length of the prefix and suffix
+    /// instead.  The content is padded (see `--align`) so that every line is exactly this wide.
+    #[arg(short = 'T', long, value_name = "chars")]
+    total_width: Option<usize>,
+
+    /// Show the content a second time after itself, flipped horizontally.
+    ///
+    /// Characters that have a mirrored version (i.e. `(` and `)`) are swapped for it.
+    #[arg(short = 'M', long)]
+    mirror: bool,
+
+    /// Prevent the marquee from looping
+    ///
+    /// This will only use the first line of the provided input.
+    #[arg(short, long = "no-loop", action = clap::ArgAction::SetFalse)]
+    _loop: bool,
+
+    /// What to do once the content has been shown once with `--no-loop`: `hold` the last frame,
+    /// `clear` it, move onto a `newline`, or `exec <cmd>` to move onto a new line and then run
+    /// `cmd` with the shell
+    #[arg(long, value_name = "action", default_value = "newline")]
+    on_finish: OnFinish,
+
+    /// Prefix to print before every output line.
+    ///
+    /// `{age}` is replaced with how long ago the message that is being shown came in (i.e. `5s` or
+    /// `3m`), unless the prefix is moved along with the content.
+    #[arg(short, long, value_name = "prefix")]
+    prefix: Option<String>,
+
+    /// Suffix to print after every output line, this can contain `{age}` like `--prefix`
+    #[arg(short = 'f', long, value_name = "suffix")]
+    suffix: Option<String>,
+
+    /// Read the prefix from a file, which is read again whenever it changes so that another
+    /// program can change it while the marquee is running
+    #[arg(long, value_name = "path", conflicts_with = "prefix")]
+    prefix_file: Option<PathBuf>,
+
+    /// Read the suffix from a file, which is read again whenever it changes (see `--prefix-file`)
+    #[arg(long, value_name = "path", conflicts_with = "suffix")]
+    suffix_file: Option<PathBuf>,
+
+    /// Move the prefix along with the content, rather than keeping it at the start of the output
+    #[arg(long)]
+    rotate_prefix: bool,
+
+    /// Move the suffix along with the content, rather than keeping it at the end of the output
+    #[arg(long)]
+    rotate_suffix: bool,
+
+    /// Separator to use between entries when looping.
+    ///
+    /// Note: This is not used when `no-loop` is set
+    #[arg(short, long, value_name = "sep", default_value_t = String::from("    "))]
+    separator: String,
+
+    /// Loop the content straight into its own start without a separator, repeating content that
+    /// fits in the output until it doesn't, which is useful for patterns (i.e. `-=-=`)
+    #[arg(long, conflicts_with_all = ["separator", "separator_list", "rows"])]
+    seamless: bool,
+
+    /// Use a different separator each time the content loops, from a comma-separated list (i.e.
+    /// `" ✦ , ✧ , ★ "`).
+    ///
+    /// This overrides `--separator`.
+    #[arg(long, value_name = "list", value_delimiter = ',')]
+    separator_list: Vec<String>,
+
+    /// The order that the separators from `--separator-list` are used in
+    #[arg(long, value_name = "order", default_value = "sequential")]
+    separator_order: SeparatorOrder,
+
+    /// The color of the separator, which shows where the content starts again
+    #[arg(long, value_name = "color")]
+    separator_color: Option<Color>,
+
+    /// Show anything in the content that matches a regular expression in a color, i.e.
+    /// `--highlight 'AAPL|MSFT=green'`.
+    ///
+    /// This can be passed multiple times, and later ones take precedence where they overlap.
+    ///
+    /// Note: This isn't used with `--figlet`, `--braille` or `--rows`
+    #[arg(long, value_name = "pattern=color")]
+    highlight: Vec<Highlight>,
+
+    /// Show the separator dimmed, which shows where the content starts again
+    #[arg(long)]
+    separator_dim: bool,
+
+    /// The number of blank frames to show between the end of the content and the start of it
+    /// again when looping, which is shown after the separator.
+    ///
+    /// Note: This is not used when `no-loop` is set
+    #[arg(short = 'G', long, value_name = "frames", default_value_t = 0)]
+    gap_frames: usize,
+
+    /// Show the whole of each new message once before it starts moving, and wait this many
+    /// milliseconds before moving it.
+    ///
+    /// This is on its own line, or it is held on the same line with `--same-line`.
+    #[arg(long, value_name = "ms", num_args = 0..=1, default_missing_value = "2000")]
+    preview: Option<u64>,
+
+    /// Show this (dimmed) until the first message comes in, rather than nothing.
+    ///
+    /// Note: it doesn't move, but the `--spinner` is shown in front of it if there is one.
+    #[arg(long, value_name = "text")]
+    placeholder: Option<String>,
+
+    /// How many characters into the content to start from each time it changes, rather than the
+    /// start of it (i.e. to carry on from where another marquee was).
+    ///
+    /// Note: This is in rows with `--rows`, and it isn't used with `--by words`
+    #[arg(long, value_name = "chars", default_value_t = 0)]
+    start_offset: usize,
+
+    /// Reverse the output (starts at the far right and move left)
+    #[arg(short, long)]
+    reverse: bool,
+
+    /// How far to move the content each time it is printed.
+    ///
+    /// `words` moves to the start of the next word, which is easier to read at slower speeds.
+    #[arg(short, long, value_name = "unit", default_value = "chars")]
+    by: ScrollUnit,
+
+    /// The direction that the text is read in.
+    ///
+    /// Right-to-left text is scrolled the opposite way (in the same way as `--reverse`, which will
+    /// flip it back).  Any right-to-left characters (i.e. Hebrew or Arabic) are always shown in
+    /// the order that they should be read.
+    #[arg(short = 'D', long, value_name = "dir", default_value = "ltr")]
+    direction: Direction,
+
+    /// The number of rows to show the content on.
+    ///
+    /// If the content doesn't fit on one row, then it is wrapped onto the next row, and the rows
+    /// are moved upwards (or downwards if reversed) instead of the content moving to the left.
+    #[arg(long, value_name = "rows", default_value_t = 1)]
+    rows: usize,
+
+    /// Draw the content in large letters using a FIGlet font, the letters are moved across all of
+    /// the rows at once.
+    ///
+    /// The font can be the path to a `.flf` file, or the name of one that is built in (`block`).
+    #[arg(
+        short = 'F',
+        long,
+        value_name = "font",
+        num_args = 0..=1,
+        default_missing_value = "block",
+        conflicts_with = "rows"
+    )]
+    figlet: Option<String>,
+
+    /// Draw the content as dots using Braille characters, which moves the content half a
+    /// character at a time.
+    ///
+    /// The letters are drawn with the `--figlet` font (`block` by default), where each character
+    /// of the font is a single dot.
+    #[arg(short = 'B', long, conflicts_with_all = ["rows", "smooth"])]
+    braille: bool,
+
+    /// Work out how wide emoji and characters with an ambiguous width (i.e. `…`) are in this
+    /// terminal before starting, by printing them and asking the terminal where the cursor is.
+    ///
+    /// Without this every character is one column wide, so wide characters (i.e. `漢` or `😀`)
+    /// make the output wider than `--width`.
+    ///
+    /// Note: This isn't used with `--figlet`, `--braille` or `--rows`
+    #[arg(long)]
+    probe_widths: bool,
+
+    /// Move the content an eighth of a character at a time, by using block characters at the
+    /// edges of the output between each step.
+    ///
+    /// Each step is split into 8 frames, which are shown `--delay / 8` apart so that the content
+    /// moves at the same speed.
+    ///
+    /// Note: This is only used with `--by chars`, and not with `--rows`
+    #[arg(long)]
+    smooth: bool,
+
+    /// The width of the progress bar that is shown when the JSON input has a `percent`
+    #[arg(long, value_name = "chars", default_value_t = 10)]
+    progress_width: usize,
+
+    /// The characters to draw the progress bar with, the filled one and then the empty one
+    #[arg(long, value_name = "chars", default_value = "#-", value_parser = text::parse_progress_chars)]
+    progress_chars: (char, char),
+
+    /// Show a spinner before the prefix, which moves on every frame (i.e. to show that a job is
+    /// still running).
+    #[arg(
+        long,
+        value_name = "style",
+        num_args = 0..=1,
+        default_missing_value = "braille"
+    )]
+    spinner: Option<Spinner>,
+
+    /// Where to show the output, this can be `stdout`, an HD44780 character LCD with an I2C
+    /// backpack (`lcd:/dev/i2c-1@0x27`), a chain of MAX7219 LED matrices
+    /// (`max7219:/dev/spidev0.0@4`, where `4` is the number of modules), or a serial device
+    /// (`serial:/dev/ttyUSB0@9600`, where `9600` is the baud rate).
+    ///
+    /// Note: The LCD, LED matrices and serial devices need marquee to be built with the
+    /// `hardware` feature
+    #[arg(short = 'O', long, value_name = "target", default_value = "stdout")]
+    output: Target,
+
+    /// Copy each line of input to stdout (or the file descriptor `fd`) as it is read, so that
+    /// the marquee can show the progress of a pipeline without getting in the way of the data
+    /// (i.e. `build | marquee --passthrough -L | tee build.log`).
+    ///
+    /// The marquee is shown on stderr when the input is copied to stdout.
+    #[arg(
+        long,
+        value_name = "fd",
+        num_args = 0..=1,
+        default_missing_value = "1",
+        conflicts_with = "status_line"
+    )]
+    passthrough: Option<u32>,
+
+    /// Text to send before each frame on a serial device (i.e. the command that a sign controller
+    /// expects), this can have escapes like `\x02` or `\r`.
+    ///
+    /// Note: This is only used when `--output` is `serial:...`
+    #[arg(long, value_name = "text", default_value = "")]
+    serial_prefix: String,
+
+    /// Text to send after each row of a frame on a serial device, this can have escapes like
+    /// `\x03` or `\r\n`.
+    ///
+    /// Note: This is only used when `--output` is `serial:...`
+    #[arg(long, value_name = "text", default_value = "\\n")]
+    serial_terminator: String,
+
+    /// Ring the terminal bell when a new message replaces the one that is being shown, so that
+    /// the window is flagged when it is in the background.
+    ///
+    /// `bel` prints the BEL character, `attention` prints the escape code that asks the terminal
+    /// for attention (i.e. in iTerm2) instead.
+    ///
+    /// Note: With `--rotate-all` the bell is rung each time it moves onto another message, and
+    /// it is only used when `--output` is `stdout`
+    #[arg(long, value_name = "kind", num_args = 0..=1, default_missing_value = "bel")]
+    bell: Option<Bell>,
+
+    /// Color each message by the log level in it, i.e. yellow for `WARN` and red for `ERROR`, so
+    /// that logs can be followed through the marquee.
+    ///
+    /// The levels are `TRACE`, `DEBUG`, `INFO`, `WARN` (or `WARNING`) and `ERROR` (or `FATAL`
+    /// and `CRITICAL`), the first one in the message is used.
+    #[arg(long)]
+    loglevel_colors: bool,
+
+    /// Show messages that match a regular expression with an effect (see `--alert-effect`), i.e.
+    /// `--alert-on ERROR`
+    #[arg(long, value_name = "regex")]
+    alert_on: Option<Regex>,
+
+    /// What happens to messages that match `--alert-on`: `flash` between `--alert-color` and the
+    /// normal style on each frame, `invert` the colors, show them in the alert `color`, or ring
+    /// the `bell` when they start being shown (in the style from `--bell`).
+    ///
+    /// Note: The bell is only rung when `--output` is `stdout`
+    #[arg(long, value_name = "effect", default_value = "flash")]
+    alert_effect: AlertEffect,
+
+    /// The color of messages that match `--alert-on`, with `--alert-effect flash` or `color`
+    #[arg(long, value_name = "color", default_value = "red")]
+    alert_color: Color,
+
+    /// Show the content differently once no input has come in for this long (i.e. `60s`, see
+    /// `--max-runtime`), so that it's clear that it may be out of date
+    #[arg(long, value_name = "duration", value_parser = parse_duration)]
+    stale_after: Option<Duration>,
+
+    /// How the content is shown once it is stale (see `--stale-after`), `plain` only adds the
+    /// `--stale-suffix`
+    #[arg(long, value_name = "style", default_value = "dim")]
+    stale_style: StaleStyle,
+
+    /// Put this after the suffix while the content is stale (i.e. `" (stale)"`, see
+    /// `--stale-after`)
+    #[arg(long, value_name = "text")]
+    stale_suffix: Option<String>,
+
+    /// Wrap each frame in the escape codes for synchronized output (mode 2026), so that the
+    /// terminal never shows half of a frame.
+    ///
+    /// `auto` only does this when stdout is a terminal, terminals that don't support it ignore
+    /// the escape codes.
+    ///
+    /// Note: This is only used with `--same-line` or `--status-line`
+    #[arg(long, value_name = "when", default_value = "auto")]
+    sync_output: When,
+
+    /// Print the output on the same line, using the `\r` escape code.
+    #[arg(short = 'L', long)]
+    same_line: bool,
+
+    /// Keep the marquee on the bottom row of the terminal, while anything else that is written
+    /// to the terminal scrolls above it (i.e. `make 2>&1 | tee /dev/tty | marquee --status-line`).
+    ///
+    /// Note: Only the first row is shown, and this is only used when `--output` is `stdout`
+    #[arg(long, conflicts_with = "same_line")]
+    status_line: bool,
+
+    /// Print every frame, even if it is the same as the one before it.
+    ///
+    /// By default, content that isn't moving is only printed once (until it changes).
+    #[arg(short, long)]
+    keep_duplicates: bool,
+
+    /// The number of columns between each tab stop, tabs in the input are expanded into spaces
+    #[arg(short, long, value_name = "cols", default_value_t = 4)]
+    tab_width: usize,
+
+    /// What to do with control characters in the input (other than tabs)
+    #[arg(short = 'C', long, value_name = "action", default_value = "strip")]
+    control_chars: ControlChars,
+
+    /// Show the content in bold (the prefix and suffix are left as they are)
+    #[arg(long)]
+    bold: bool,
+
+    /// Show the content dimmed (the prefix and suffix are left as they are)
+    #[arg(long)]
+    dim: bool,
+
+    /// Show the content in italics (the prefix and suffix are left as they are)
+    #[arg(long)]
+    italic: bool,
+
+    /// Underline the content (the prefix and suffix are left as they are)
+    #[arg(long)]
+    underline: bool,
+
+    /// Make the content blink (the prefix and suffix are left as they are)
+    #[arg(long)]
+    blink: bool,
+
+    /// Replace text in the content using a sed-style substitution, `s/pattern/replacement/flags`.
+    ///
+    /// The pattern is a regular expression, and the replacement can refer to groups using `\1` or
+    /// `$1`.  The `g` flag replaces every match rather than just the first, and the `i` flag
+    /// makes the pattern case-insensitive.  This can be passed multiple times, and each one is
+    /// applied in order.
+    #[arg(short = 'x', long, value_name = "s/pattern/replacement/")]
+    replace: Vec<Replace>,
+
+    /// Only show lines of input that match a regular expression, the rest are ignored (i.e.
+    /// `--only 'now playing'`).
+    ///
+    /// Note: Empty lines still clear the marquee, and the whole line is matched with `--json`
+    #[arg(long, value_name = "regex")]
+    only: Option<Regex>,
+
+    /// Ignore lines of input that match a regular expression (see `--only`)
+    #[arg(long, value_name = "regex")]
+    ignore: Option<Regex>,
+
+    /// Convert the content to uppercase (the prefix and suffix are left as they are)
+    #[arg(short = 'U', long, conflicts_with_all = ["lower", "title"])]
+    upper: bool,
+
+    /// Convert the content to lowercase (the prefix and suffix are left as they are)
+    #[arg(short = 'W', long, conflicts_with = "title")]
+    lower: bool,
+
+    /// Convert the content to title case (the prefix and suffix are left as they are)
+    #[arg(short = 'I', long)]
+    title: bool,
+
+    /// The maximum length of a line of input in bytes.
+    ///
+    /// Anything past this is never stored, so a huge line can't use up all of the memory.
+    #[arg(long, value_name = "bytes")]
+    max_input_len: Option<usize>,
+
+    /// What to do with lines that are longer than `--max-input-len`
+    #[arg(long, value_name = "policy", default_value = "truncate")]
+    max_input_policy: MaxInputPolicy,
+
+    /// End each line at a carriage return (`\r`) as well as a newline, so that the progress of
+    /// programs that keep rewriting one line (i.e. curl, wget or pip) is shown as it changes.
+    ///
+    /// Note: Empty lines are ignored with this, since there is one between `\r` and `\n`
+    #[arg(long)]
+    split_cr: bool,
+
+    /// The character encoding of the input (i.e. `latin1` or `shift-jis`), which is decoded
+    /// before it is shown rather than the input having to be UTF-8.
+    ///
+    /// Any label from the WHATWG Encoding Standard can be used, as long as a newline is a single
+    /// `\n` byte in the encoding (so not UTF-16).  Anything that isn't valid in it is shown as `�`.
+    #[arg(long, value_name = "encoding", value_parser = parse_encoding)]
+    encoding: Option<&'static Encoding>,
+
+    /// What to do with a line that isn't valid UTF-8: `replace` the invalid bytes with `�`,
+    /// `skip` the line, or stop with an `error`.
+    ///
+    /// Note: This isn't used with `--encoding`, which always replaces them
+    #[arg(long, value_name = "policy", default_value = "error")]
+    invalid_utf8: InvalidUtf8,
+
+    /// If the input will be passed in as JSON
+    #[arg(short, long)]
+    json: bool,
+
+    /// Read the messages from a command instead of stdin, which is run with the shell.
+    ///
+    /// Each line that it prints is a message in JSON (see `--json`).  It is sent a line of JSON
+    /// on its stdin when something happens: `{"event":"shown","message":"..."}` when a message
+    /// starts being shown, `{"event":"cycle_end"}` when a cycle of it is finished, and
+    /// `{"event":"skip"}` when it is skipped.
+    #[arg(long, value_name = "cmd")]
+    source_cmd: Option<String>,
+
+    /// Start `--source-cmd` again whenever it exits, rather than treating it like stdin being
+    /// closed.
+    ///
+    /// It waits a second before starting it again, which is doubled (up to a minute) each time it
+    /// exits again within a minute of being started.  The number of times that it was started
+    /// again is part of `--stats` and `--metrics`.
+    #[arg(long, requires = "source_cmd")]
+    restart_source: bool,
+
+    /// Split the output into two panes that move on their own, with the messages from stdin on
+    /// the left and each line that `cmd` prints (which is run with the shell) on the right.
+    ///
+    /// Both panes are padded to their width (see `--align`), so the right pane always starts in
+    /// the same place.  The prefix, suffix and spinner are only around the left pane.
+    #[arg(long, value_name = "cmd", conflicts_with_all = ["figlet", "braille"])]
+    right: Option<String>,
+
+    /// The width of the right pane (see `--right`), this is `--width` by default
+    #[arg(long, value_name = "chars")]
+    right_width: Option<usize>,
+
+    /// What to put between the two panes (see `--right`)
+    #[arg(long, value_name = "sep", default_value = " | ")]
+    pane_separator: String,
+
+    /// Pass each message through a command before it is shown (i.e. to translate it), which is
+    /// run with the shell.
+    ///
+    /// The message is written to its stdin, and what it prints is shown instead.  If it fails, or
+    /// it takes longer than a second (when it is killed), then the message is shown as it is.
+    #[arg(long, value_name = "cmd")]
+    filter_cmd: Option<String>,
+
+    /// Pass each frame through `--filter-cmd` instead of each message, with a line for each row
+    ///
+    /// Note: This is only used with `--filter-cmd`, and it runs the command for every frame
+    #[arg(long)]
+    filter_frames: bool,
+
+    /// Treat lines that start with `sigil` as commands that change the marquee, rather than
+    /// showing them (i.e. `:` for `:speed 100`).
+    ///
+    /// The commands are `speed <ms>`, `pause`, `resume`, `clear`, `skip`, `flush` (which removes
+    /// every message other than the current one with `--rotate-all`), and `sep <separator>`
+    /// (where the separator can be a JSON string, like `" | "`).
+    ///
+    /// With `--json`, the same commands can be given as `{"type": "control", "action": "..."}`
+    /// without this being set.
+    #[arg(long, value_name = "sigil")]
+    control_sigil: Option<String>,
+
+    /// Read control commands from this FIFO (i.e. one made with `mkfifo`) as well as the input, so
+    /// that they don't have to be mixed in with the content.
+    ///
+    /// Each line is one of the commands from `--control-sigil` without the sigil, or the same
+    /// command as JSON (i.e. `{"action": "speed", "delay": 100}`).
+    #[arg(long, value_name = "path")]
+    control_fifo: Option<PathBuf>,
+
+    /// Stop when a line isn't valid JSON, rather than ignoring it
+    ///
+    /// Note: This is only used when `json` is set
+    #[arg(long)]
+    strict: bool,
+
+    /// What to show for a line with `"visible": false` in the JSON: `blank` space instead of the
+    /// content (along with the prefix and suffix), or the `previous` line that was shown.
+    ///
+    /// Note: With `--rotate-all` hidden lines are always left out, and this is only used when
+    /// `json` is set
+    #[arg(long, value_name = "what", default_value = "blank")]
+    on_hidden: OnHidden,
+
+    /// Cycle through every distinct line received instead of only showing the latest one.
+    ///
+    /// An empty line clears the list of lines.  Sending `SIGUSR1` to the process will skip the rest
+    /// of the current line and move onto the next one.
+    #[arg(short = 'a', long)]
+    rotate_all: bool,
+
+    /// The number of full cycles to show each line for before moving onto the next one.
+    ///
+    /// This can be overridden for a single line using `repeat` in the JSON input.
+    ///
+    /// Note: This is only used when `rotate-all` is set
+    #[arg(short = 'R', long, value_name = "cycles", default_value_t = 1)]
+    repeat: usize,
+
+    /// Show the lines in a random order each round.
+    ///
+    /// Note: This is only used when `rotate-all` is set
+    #[arg(short = 'S', long)]
+    shuffle: bool,
+
+    /// Print a summary of how the marquee ran (i.e. how many frames were late) to stderr when it
+    /// exits.
+    ///
+    /// The summary can also be printed while it is running by sending `SIGUSR2` to the process.
+    #[arg(long)]
+    stats: bool,
+
+    /// Record each frame that is shown to a file, along with when it was shown and the message
+    /// that it came from.
+    ///
+    /// Each frame is written as a line of JSON.
+    #[arg(long, value_name = "file")]
+    record: Option<PathBuf>,
+
+    /// Publish each frame that is shown to an MQTT broker (i.e. `mqtt://localhost/marquee`), so
+    /// that other displays can subscribe to the same animation.
+    ///
+    /// The rows of each frame are joined with `\n`.
+    #[arg(long, value_name = "url")]
+    publish: Option<Broker>,
+
+    /// Only publish the message when it changes, rather than every frame
+    ///
+    /// Note: This is only used with `--publish`
+    #[arg(long)]
+    publish_messages: bool,
+
+    /// Send a desktop notification with the whole message each time a new one is shown, while
+    /// the marquee carries on scrolling it.
+    ///
+    /// Note: This needs `notify-send` (from libnotify) to be installed
+    #[arg(long)]
+    notify_on_change: bool,
+
+    /// Run this command with the shell each time a new message is shown, i.e. to log it or show it
+    /// somewhere else as well.
+    ///
+    /// The content is in the `MARQUEE_MESSAGE` environment variable, and the whole line of input
+    /// (i.e. the JSON with `--json`) is written to its stdin.  The marquee doesn't wait for it.
+    #[arg(long, value_name = "cmd")]
+    on_change: Option<String>,
+
+    /// Run this command with the shell each time the content has been scrolled through once (i.e.
+    /// to move a playlist onto the next song), with the content in `MARQUEE_MESSAGE`.
+    ///
+    /// Note: The marquee doesn't wait for it, so it should be quick with a short `--delay`
+    #[arg(long, value_name = "cmd")]
+    on_cycle: Option<String>,
+
+    /// Write the marquee to an asciinema cast instead of printing it.
+    ///
+    /// All of stdin is read first, and then `--cycles` cycles are written straight away, with
+    /// `--delay` between each frame.
+    #[arg(long, value_name = "file.cast")]
+    export_cast: Option<PathBuf>,
+
+    /// The number of full cycles of the content to write to the cast (or `export`)
+    ///
+    /// Note: This is only used with `export-cast` or `export`
+    #[arg(long, value_name = "cycles", default_value_t = 1)]
+    cycles: usize,
+
+    /// Serve the same stats as `--stats` as Prometheus metrics at `http://<addr>/metrics` (i.e.
+    /// `127.0.0.1:9100`)
+    #[arg(long, value_name = "addr")]
+    metrics: Option<String>,
+
+    /// Exit once stdin (or the output of `--source-cmd`) has been closed.
+    ///
+    /// `immediate` exits as soon as stdin is closed, `after-cycle` finishes the current cycle of
+    /// the line that is being shown first.
+    ///
+    /// Note: Without this the marquee still exits in the same way if stdin is closed before
+    /// anything has been shown, since there will never be anything to show
+    #[arg(
+        short,
+        long,
+        value_name = "when",
+        num_args = 0..=1,
+        default_missing_value = "immediate"
+    )]
+    exit_on_eof: Option<ExitWhen>,
+
+    /// Exit after running for this long (i.e. `30s`, `5m` or `500ms`, a number on its own is in
+    /// seconds), so that a banner can be shown for a while
+    #[arg(long, value_name = "duration", value_parser = parse_duration)]
+    max_runtime: Option<Duration>,
+
+    /// When to exit once `--max-runtime` is up, `immediate` exits straight away and
+    /// `after-cycle` finishes the current cycle of the line that is being shown first
+    #[arg(long, value_name = "when", default_value = "immediate")]
+    max_runtime_policy: ExitWhen,
+
+    /// Exit (with a status of 0) once no new messages have come in for this long (i.e. `30s`, see
+    /// `--max-runtime`), so that a marquee that is showing the output of a job that has finished
+    /// doesn't stay around forever
+    #[arg(long, value_name = "duration", value_parser = parse_duration)]
+    exit_after_idle: Option<Duration>,
+}
+
+impl Cli {
+    /// The time between each frame, which is shorter with `--smooth` since each step is split
+    /// into several frames
+    fn frame_delay(&self) -> Duration {
+        self.delay_for(Duration::from_millis(self.delay))
+    }
+
+    /// The time between each frame for a step of `delay` (see `frame_delay`)
+    fn delay_for(&self, delay: Duration) -> Duration {
+        if self.smooth && self.by == ScrollUnit::Chars && self.rows <= 1 {
+            delay / text::SMOOTH_STEPS as u32
+        } else {
+            delay
+        }
+    }
+}
+
+/// Parse a duration for `--max-runtime`, which is a number followed by `ms`, `s`, `m` or `h`
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("`{}` doesn't start with a number", s))?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 60.0 * 60.0,
+        unit => {
+            return Err(format!(
+                "unknown unit `{}`, it can be `ms`, `s`, `m` or `h`",
+                unit
+            ))
+        }
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|e| e.to_string())
+}
+
+/// Parse an encoding for `--encoding`, which can be written with `-` instead of `_` (i.e.
+/// `shift-jis`)
+fn parse_encoding(s: &str) -> Result<&'static Encoding, String> {
+    let encoding = Encoding::for_label(s.as_bytes())
+        .or_else(|| Encoding::for_label(s.replace('-', "_").as_bytes()))
+        .ok_or_else(|| format!("unknown encoding `{}`", s))?;
+    if !encoding.is_ascii_compatible() {
+        return Err(format!(
+            "`{}` can't be used, since a newline isn't a single byte in it",
+            encoding.name()
+        ));
+    }
+    Ok(encoding)
+}
+
+/// Something to do other than showing stdin in the marquee
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Play back a recording that was made with `--record`, with the same timing that it was
+    /// recorded with.
+    ///
+    /// Options that change how frames are printed (i.e. `--same-line`) are passed before
+    /// `replay`, the rest are already part of the recorded frames.
+    Replay {
+        /// The recording to play back
+        file: PathBuf,
+
+        /// How many times faster to play back the recording (i.e. `0.5` is half speed)
+        #[arg(short, long, value_name = "multiplier", default_value_t = 1.0, value_parser = replay::parse_speed)]
+        speed: f64,
+    },
+    /// Write `--cycles` cycles of the content from stdin to a file, rather than showing it.
+    ///
+    /// Options that change the content (i.e. `--width`) are passed before `export`.
+    Export {
+        /// Write the cycles to an animated SVG
+        #[arg(long, value_name = "file.svg")]
+        svg: PathBuf,
+
+        /// The color of the text in the SVG
+        #[arg(long, value_name = "color", default_value_t = String::from("#d0d0d0"))]
+        foreground: String,
+
+        /// The color behind the text in the SVG
+        #[arg(long, value_name = "color", default_value_t = String::from("#1e1e1e"))]
+        background: String,
+    },
+    /// Work out lots of frames of generated content as fast as possible without showing them,
+    /// and print how many frames were worked out per second, so that changes that make the
+    /// marquee slower are easy to spot.
+    ///
+    /// Options that change the content (i.e. `--mode`) are passed before `bench`, other than the
+    /// width which is set here.  With `--json` the content is sent as the `content` of a message.
+    ///
+    /// Note: The allocations are only counted when it is built with the `bench` feature
+    Bench {
+        /// The number of frames to work out for each length and width
+        #[arg(short, long, value_name = "frames", default_value_t = 100_000)]
+        frames: usize,
+
+        /// The lengths of the content to time, in characters
+        #[arg(
+            long,
+            value_name = "chars",
+            value_delimiter = ',',
+            default_value = "10,100,1000,10000"
+        )]
+        lengths: Vec<usize>,
+
+        /// The widths of the output to time each length with
+        #[arg(
+            long,
+            value_name = "chars",
+            value_delimiter = ',',
+            default_value = "20,80"
+        )]
+        widths: Vec<usize>,
+    },
+}
+
+/// What to do once the content has been shown once (see `--on-finish`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OnFinish {
+    /// Leave the last frame where it is
+    Hold,
+    /// Remove the last frame
+    Clear,
+    /// Move onto a new line
+    Newline,
+    /// Move onto a new line and then run the command
+    Exec(String),
+}
+
+impl std::str::FromStr for OnFinish {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(' ') {
+            Some(("exec", cmd)) if !cmd.trim().is_empty() => Ok(OnFinish::Exec(cmd.into())),
+            _ => match s {
+                "hold" => Ok(OnFinish::Hold),
+                "clear" => Ok(OnFinish::Clear),
+                "newline" => Ok(OnFinish::Newline),
+                "exec" => Err(String::from(
+                    "`exec` needs a command (i.e. `exec echo done`)",
+                )),
+                _ => Err(format!(
+                    "`{}` isn't one of `hold`, `clear`, `newline` or `exec <cmd>`",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// What to show for a hidden line (see `--on-hidden`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OnHidden {
+    /// Blank space the same width as the output
+    Blank,
+    /// Carry on showing the line from before
+    Previous,
+}
+
+/// How content that hasn't been updated for a while is shown (see `--stale-after`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum StaleStyle {
+    Dim,
+    Italic,
+    /// The same as the rest of the content
+    Plain,
+}
+
+/// How much earlier or later than the delay each frame can be shown (see `--jitter`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Jitter {
+    Millis(u64),
+    Percent(f64),
+}
+
+impl std::str::FromStr for Jitter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_suffix('%') {
+            Some(percent) => match percent.trim().parse() {
+                Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(Jitter::Percent(percent)),
+                _ => Err(format!("`{}` isn't a percentage from 0 to 100", percent)),
+            },
+            None => s
+                .strip_suffix("ms")
+                .unwrap_or(s)
+                .trim()
+                .parse()
+                .map(Jitter::Millis)
+                .map_err(|_| format!("`{}` isn't a number of milliseconds or a percentage", s)),
+        }
+    }
+}
+
+impl Jitter {
+    /// When to show the frame that is due at `deadline`, where `nudge` is how far it is moved
+    /// from -1 (as early as it can be) to 1 (as late as it can be)
+    fn apply(&self, deadline: Instant, wait_time: Duration, nudge: f64) -> Instant {
+        let max = match *self {
+            Jitter::Millis(ms) => Duration::from_millis(ms),
+            Jitter::Percent(percent) => wait_time.mul_f64(percent / 100.0),
+        };
+        let offset = max.mul_f64(nudge.abs());
+        if nudge < 0.0 {
+            deadline.checked_sub(offset).unwrap_or(deadline)
+        } else {
+            deadline + offset
+        }
+    }
+}
+
+/// When to exit after stdin has been closed or the marquee has run for long enough (see
+/// `--exit-on-eof` and `--max-runtime`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitWhen {
+    /// Exit straight away
+    Immediate,
+    /// Finish the current cycle and then exit
+    AfterCycle,
+}
+
+/// What decides when each frame is shown (see `--clock`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Clock {
+    /// Show a frame every `--delay` milliseconds
+    Wall,
+    /// Show a frame for each line of `--tick-file`
+    External,
+}
+
+/// What to do when frames are shown late (see `--catch-up`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum CatchUp {
+    /// Move the content on without showing the frames that were missed
+    Skip,
+    /// Show the frames that were missed straight away
+    Burst,
+    /// Carry on from where it was, so the content falls behind
+    None,
+}
+
+/// How content that is wider than the output is shown (see `--mode`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Move the content across the output
+    Scroll,
+    /// Cut off the end of the content
+    Truncate,
+}
+
+/// Where to put content that fits in the output (see `--align`)
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// The order that separators are used in (see `--separator-order`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum SeparatorOrder {
+    /// Go through the list in order, starting again at the end
+    Sequential,
+    /// Pick one at random each time
+    Random,
+}
+
+/// The style of the spinner (see `--spinner`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Spinner {
+    /// A dot moving around a Braille character (`⠋⠙⠹`)
+    Braille,
+    /// Dots that fill up and then empty (`. .. ...`)
+    Dots,
+    /// A line that turns around (`- \ | /`)
+    Line,
+}
+
+/// When to do something that only makes sense in a terminal (see `--sync-output` and `--animate`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum When {
+    /// Only when stdout is a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+/// What happens to messages that match `--alert-on` (see `--alert-effect`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum AlertEffect {
+    /// Switch between `--alert-color` and the normal style on each frame
+    Flash,
+    /// Swap the foreground and background colors
+    Invert,
+    /// Show them in `--alert-color`
+    Color,
+    /// Ring the bell when they start being shown
+    Bell,
+}
+
+/// How to ring the bell (see `--bell`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Bell {
+    /// The BEL character (`\x07`)
+    Bel,
+    /// The `RequestAttention` escape code
+    Attention,
+}
+
+/// A color that text can be shown in (see `--separator-color`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+/// How far the content moves each time (see `--by`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ScrollUnit {
+    /// Move a single character at a time
+    Chars,
+    /// Move to the start of the next word
+    Words,
+}
+
+/// The direction that text is read in (see `--direction`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    /// Left-to-right
+    Ltr,
+    /// Right-to-left
+    Rtl,
+}
+
+/// What to do with control characters in the input (see `--control-chars`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlChars {
+    /// Remove them from the input
+    Strip,
+    /// Replace them with a printable version (i.e. `^[` or `\u{85}`)
+    Escape,
+}
+
+/// What to do with lines that are too long (see `--max-input-policy`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum MaxInputPolicy {
+    /// Cut the line off at the maximum length
+    Truncate,
+    /// Ignore the line
+    Reject,
+    /// Print an error and exit
+    Error,
+}
+
+/// What to do with lines that aren't valid UTF-8 (see `--invalid-utf8`)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum InvalidUtf8 {
+    /// Replace each invalid sequence with the replacement character
+    Replace,
+    /// Ignore the line
+    Skip,
+    /// Print an error and exit
+    Error,
+}
+
+/// Run the marquee with the arguments that the process was started with
+pub fn run() -> Result<Exit, Error> {
+    let mut options = Cli::parse();
+    // The messages from a plugin are always JSON
+    options.json |= options.source_cmd.is_some();
+    // The frames are only moved if someone can see them move, which isn't the case when they
+    // aren't being shown at all (i.e. `marquee bench` or `--export-cast`)
+    let terminal = match options.passthrough {
+        Some(1) => io::stderr().is_terminal(),
+        _ => io::stdout().is_terminal(),
+    };
+    let shown = options.command.is_none() && options.export_cast.is_none();
+    // `--no-loop` and `--delay 0` are asking for every frame, even when it isn't a terminal
+    let pipeline = !options._loop || options.delay == 0;
+    let animate = match options.animate {
+        When::Auto => !shown || options.output != Target::Stdout || terminal || pipeline,
+        When::Always => true,
+        When::Never => false,
+    };
+    if !animate {
+        options.mode = Mode::Truncate;
+    }
+    env_logger::Builder::new()
+        .filter_level(match options.verbose {
+            0 => log::LevelFilter::Warn,
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        })
+        .parse_default_env()
+        .init();
+
+    match &options.command {
+        Some(Command::Replay { file, speed }) => {
+            let mut printer = output::open(&options).map_err(Error::Output)?;
+            let result = replay::replay(file, *speed, printer.as_mut());
+            let finished = printer.finish().map_err(Error::Output);
+            return result.and_then(|exit| finished.map(|()| exit));
+        }
+        Some(Command::Export {
+            svg,
+            foreground,
+            background,
+        }) => return export::svg(&options, svg, foreground, background),
+        Some(Command::Bench {
+            frames,
+            lengths,
+            widths,
+        }) => return bench::bench(&options, *frames, lengths, widths),
+        None => {}
+    }
+
+    if let Some(path) = &options.export_cast {
+        return cast::export(&options, path);
+    }
+
+    let mut wait_time = options.frame_delay();
+
+    // Set when the current message should be skipped
+    let skip = Arc::new(AtomicBool::new(false));
+    #[cfg(unix)]
+    signal_hook::flag::register(signal_hook::consts::SIGUSR1, Arc::clone(&skip))
+        .map_err(|err| Error::Signal(signal_hook::consts::SIGUSR1, err))?;
+
+    // This has to be done before anything reads stdin, since the terminal replies on it
+    let widths = match options.probe_widths && options.figlet.is_none() && !options.braille {
+        true => Widths::probe()
+            .inspect_err(|err| log::warn!("Couldn't work out the widths of characters: {}", err))
+            .ok(),
+        false => None,
+    };
+
+    let (events, rx) = mpsc::channel();
+    if let Some(path) = &options.prefix_file {
+        options.prefix = Some(input::read_decoration(path).map_err(Error::Decoration)?);
+        input::spawn_watch(events.clone(), path.clone(), Event::Prefix);
+    }
+    if let Some(path) = &options.suffix_file {
+        options.suffix = Some(input::read_decoration(path).map_err(Error::Decoration)?);
+        input::spawn_watch(events.clone(), path.clone(), Event::Suffix);
+    }
+    // The stats should still be printed when the marquee is stopped by a signal
+    #[cfg(unix)]
+    if options.stats {
+        use signal_hook::consts::{SIGINT, SIGTERM, SIGUSR2};
+        input::spawn_signals(events.clone(), &[SIGUSR2, SIGINT, SIGTERM])?;
+    }
+    if let Some(path) = &options.control_fifo {
+        input::spawn_control(events.clone(), path).map_err(Error::ControlFifo)?;
+    }
+    if let (Clock::External, Some(path)) = (options.clock, &options.tick_file) {
+        let file = File::open(path).map_err(Error::Ticks)?;
+        input::spawn_ticks(events.clone(), file);
+    }
+    let read = ReadOptions::from_options(&options);
+    // This is only kept so that the command is stopped along with the marquee
+    let _right = match &options.right {
+        Some(cmd) => Some(RightPane::spawn(cmd, events.clone(), read).map_err(Error::Right)?),
+        None => None,
+    };
+
+    let mut source = match &options.source_cmd {
+        Some(cmd) => {
+            Some(Source::spawn(cmd, events, read, options.restart_source).map_err(Error::Source)?)
+        }
+        None => {
+            input::spawn_stdin(events, read);
+            None
+        }
+    };
+
+    let mut stats = Stats::default();
+    // The metrics server gets a copy of the stats, which is updated every time around the loop
+    let shared_stats = match &options.metrics {
+        Some(addr) => {
+            let shared = Arc::new(Mutex::new(Stats::default()));
+            metrics::serve(addr, Arc::clone(&shared)).map_err(Error::Metrics)?;
+            Some(shared)
+        }
+        None => None,
+    };
+    let mut printer = output::open(&options).map_err(Error::Output)?;
+    let mut recorder = match &options.record {
+        Some(path) => Some(Recorder::create(path).map_err(Error::Record)?),
+        None => None,
+    };
+    let mut publisher = match &options.publish {
+        Some(broker) => {
+            Some(Publisher::connect(broker, options.publish_messages).map_err(Error::Publish)?)
+        }
+        None => None,
+    };
+    let mut notifier = options.notify_on_change.then(Notifier::default);
+    let mut on_change = options.on_change.clone().map(OnChange::new);
+    let mut passthrough = match options.passthrough {
+        Some(1) => Some(Box::new(io::stdout()) as Box<dyn Write>),
+        // Any other file descriptor is opened through `/dev/fd`, which fails if it isn't open
+        Some(fd) => Some(Box::new(
+            File::options()
+                .append(true)
+                .open(format!("/dev/fd/{}", fd))
+                .map_err(Error::Passthrough)?,
+        ) as Box<dyn Write>),
+        None => None,
+    };
+    let font = Font::from_options(&options).map_err(Error::Font)?;
+    let mut marquee = Marquee::new(options.clone(), font);
+    if let Some(widths) = widths {
+        marquee.set_widths(widths);
+    }
+    let sync = match &options.sync_group {
+        Some(name) => Some(SyncClock::join(name, wait_time).map_err(Error::Sync)?),
+        None => None,
+    };
+    // The frame of the shared clock that was shown last (`--sync-group`)
+    let mut last_tick = None;
+    let mut eof = false;
+    // When the next frame should be shown, and when to stop (`--max-runtime`)
+    let mut deadline = Instant::now();
+    let end = options.max_runtime.map(|runtime| deadline + runtime);
+    // The ticks that haven't been shown yet, and if no more can come in (`--clock external`)
+    let mut ticks = 0;
+    let mut ticks_closed = false;
+    // If the marquee has been paused by a control command
+    let mut paused = false;
+    // How far the next frame is moved by `--jitter`, which is picked again after each frame
+    let mut nudge = 0.0;
+    // What is shown until the first message comes in (`--placeholder`)
+    let mut placeholder = options.placeholder.as_deref().map(Rc::<str>::from);
+    // When the last line of input came in, to know when the content is stale (`--stale-after`)
+    let mut last_input = Instant::now();
+
+    // Everything happens in this loop, which handles events from the inputs until it is time to
+    // show the next frame
+    let result = loop {
+        if let Some(shared) = &shared_stats {
+            shared
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone_from(&stats);
+        }
+
+        if eof && marquee.is_empty() && options.exit_on_eof.is_some() {
+            break Ok(Exit::Eof);
+        }
+
+        let idle_end = options.exit_after_idle.map(|idle| last_input + idle);
+        if idle_end.is_some_and(|idle_end| Instant::now() >= idle_end) {
+            break Ok(Exit::Idle);
+        }
+
+        // The current cycle can only be finished if there is something to show
+        let timed_out = end.is_some_and(|end| Instant::now() >= end);
+        let waiting =
+            marquee.is_empty() || paused || (options.clock == Clock::External && ticks == 0);
+        if timed_out && (options.max_runtime_policy == ExitWhen::Immediate || waiting) {
+            break Ok(Exit::Timeout);
+        }
+
+        // Every tick has been shown (or they can never be shown), so there's nothing left to do
+        if ticks_closed && (ticks == 0 || (eof && marquee.is_empty())) {
+            break Ok(Exit::Ticks);
+        }
+
+        let due = match options.jitter {
+            Some(jitter) if sync.is_none() => jitter.apply(deadline, wait_time, nudge),
+            _ => deadline,
+        };
+        let timeout = due.saturating_duration_since(Instant::now());
+        let event =
+            if options.clock == Clock::External && ticks > 0 && !marquee.is_empty() && !paused {
+                // Handle anything that has already come in, and then show the next frame
+                rx.try_recv().ok()
+            } else if (marquee.is_empty() && placeholder.is_none())
+                || options.clock == Clock::External
+                || paused
+            {
+                // There is nothing to show until something comes in, so there's no reason to wake up
+                // for each frame (other than to stop for `--max-runtime` or `--exit-after-idle`)
+                let wake = end.into_iter().chain(idle_end).min();
+                let event = match wake {
+                    Some(wake) => rx.recv_timeout(wake.saturating_duration_since(Instant::now())),
+                    None => rx.recv().map_err(RecvTimeoutError::from),
+                };
+                match event {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    // Nothing else can come in, so there will never be anything to show, which is
+                    // the same as `--exit-on-eof` (even when it isn't set)
+                    Err(RecvTimeoutError::Disconnected) => match wake {
+                        Some(wake) => {
+                            thread::sleep(wake.saturating_duration_since(Instant::now()));
+                            continue;
+                        }
+                        None => break Ok(Exit::Eof),
+                    },
+                }
+            } else {
+                match rx.recv_timeout(timeout) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    // Nothing else can come in, so just wait for the next frame
+                    Err(RecvTimeoutError::Disconnected) => {
+                        thread::sleep(timeout);
+                        None
+                    }
+                }
+            };
+
+        // A line of input can be a control command as well (`--control-sigil`)
+        let event = match event {
+            Some(Event::Line(line)) => {
+                if let Some(out) = &mut passthrough {
+                    if let Err(err) = writeln!(out, "{}", line).and_then(|_| out.flush()) {
+                        break Err(Error::Passthrough(err));
+                    }
+                }
+                match control::from_line(&options, &line) {
+                    Some(action) => Some(Event::Control(action)),
+                    None => Some(Event::Line(line)),
+                }
+            }
+            event => event,
+        };
+
+        match event {
+            Some(Event::Control(action)) => {
+                let action = match action {
+                    Ok(action) => action,
+                    Err(err) => {
+                        log::warn!("Ignoring an invalid control command: {}", err);
+                        continue;
+                    }
+                };
+                log::info!("Control command: {:?}", action);
+                match action {
+                    Action::Speed { .. } if sync.is_some() => {
+                        log::warn!("The speed can't be changed in a sync group");
+                    }
+                    Action::Speed { delay } => {
+                        options.delay = delay;
+                        marquee.set_delay(delay);
+                        wait_time = options.frame_delay();
+                        deadline = Instant::now();
+                    }
+                    Action::Pause => paused = true,
+                    Action::Resume => {
+                        paused = false;
+                        deadline = Instant::now();
+                    }
+                    Action::Clear => {
+                        marquee.push(String::new(), None);
+                    }
+                    Action::Skip => skip.store(true, Ordering::Relaxed),
+                    Action::Flush => marquee.flush(),
+                    Action::Separator { separator } => marquee.set_separator(separator),
+                }
+                continue;
+            }
+            Some(Event::Line(mut line)) => {
+                let wanted = options.only.as_ref().is_none_or(|r| r.is_match(&line))
+                    && !options.ignore.as_ref().is_some_and(|r| r.is_match(&line));
+                if !line.is_empty() && !wanted {
+                    log::debug!("Ignoring a line that was filtered out: {:?}", line);
+                    continue;
+                }
+
+                stats.messages += 1;
+                last_input = Instant::now();
+                if !line.is_empty() {
+                    placeholder = None;
+                }
+                if let (Some(cmd), false) = (&options.filter_cmd, options.filter_frames) {
+                    match filter::filter(cmd, &line) {
+                        Ok(Some(filtered)) => line = filtered,
+                        Ok(None) => {}
+                        Err(err) => break Err(Error::Filter(err)),
+                    }
+                }
+                // Show the new message straight away, rather than waiting for the next frame
+                if marquee.push(line, Some(Instant::now())) {
+                    deadline = Instant::now();
+                }
+                continue;
+            }
+            Some(Event::RightLine(line)) => {
+                placeholder = None;
+                if marquee.push_right(line, Some(Instant::now())) {
+                    deadline = Instant::now();
+                }
+                continue;
+            }
+            Some(Event::Prefix(prefix)) => {
+                marquee.set_prefix(prefix);
+                continue;
+            }
+            Some(Event::Suffix(suffix)) => {
+                marquee.set_suffix(suffix);
+                continue;
+            }
+            Some(Event::Eof) => {
+                log::info!("Stdin has been closed");
+                eof = true;
+                continue;
+            }
+            Some(Event::SourceExited) => {
+                if let Some(source) = &mut source {
+                    source.exited();
+                }
+                continue;
+            }
+            Some(Event::RestartSource) => {
+                if let Some(Err(err)) = source.as_mut().map(Source::restart) {
+                    break Err(Error::Source(err));
+                }
+                stats.restarts += 1;
+                continue;
+            }
+            Some(Event::Tick) => {
+                // Ticks that come in before there is anything to show are kept until there is
+                ticks += 1;
+                continue;
+            }
+            Some(Event::TicksClosed) => {
+                ticks_closed = true;
+                continue;
+            }
+            Some(Event::Error(err)) => break Err(err),
+            #[cfg(unix)]
+            Some(Event::Signal(signal_hook::consts::SIGUSR2)) => {
+                eprint!("{}", stats);
+                continue;
+            }
+            #[cfg(unix)]
+            Some(Event::Signal(signal)) => {
+                // Clean up and show the stats, and then let the signal stop the process like it
+                // normally would
+                let _ = printer.finish();
+                eprint!("{}", stats);
+                signal_hook::low_level::emulate_default_handler(signal)
+                    .map_err(|err| Error::Signal(signal, err))?;
+                continue;
+            }
+            None => {}
+        }
+
+        if eof && options.exit_on_eof == Some(ExitWhen::Immediate) {
+            break Ok(Exit::Eof);
+        }
+
+        if paused {
+            continue;
+        }
+
+        if skip.swap(false, Ordering::Relaxed) {
+            log::info!("Skipping the current message");
+            marquee.skip();
+            if let Some(source) = &mut source {
+                source.send(Control::Skip);
+            }
+        }
+
+        // Whether the marquee should stop after this frame, and why
+        let stop = |frame: &Frame| {
+            if !options._loop && frame.finished {
+                // Break after printing everything when `--no-loop` is passed
+                Some(Exit::Finished)
+            } else if eof && options.exit_on_eof == Some(ExitWhen::AfterCycle) && frame.cycle_end {
+                // Stop at the end of the cycle once stdin has been closed
+                Some(Exit::Eof)
+            } else if timed_out && frame.cycle_end {
+                Some(Exit::Timeout)
+            } else {
+                None
+            }
+        };
+
+        // Work out when the next frame should be shown, and how many frames were missed if this
+        // one is late
+        let now = Instant::now();
+        let mut missed = 0;
+        if options.clock == Clock::External {
+            // Frames are never late with an external clock, each tick is exactly one frame
+            ticks -= 1;
+        } else if let Some(sync) = &sync {
+            // Every marquee in the group shows the same frame at the same time, so any that were
+            // missed are always skipped to keep up with the others
+            let (tick, next) = sync.tick();
+            if let Some(last) = last_tick {
+                missed = tick.saturating_sub(last + 1);
+                if missed > 0 {
+                    stats.late += 1;
+                    log::debug!("Frame is {} frames late (sync group)", missed);
+                }
+            }
+            last_tick = Some(tick);
+            deadline = now + next;
+            marquee.sync(tick);
+        } else {
+            let late = now.saturating_duration_since(deadline);
+            if !wait_time.is_zero() && late >= wait_time {
+                stats.late += 1;
+                log::debug!("Frame is {:?} late ({:?} catch-up)", late, options.catch_up);
+            }
+            match options.catch_up {
+                CatchUp::None => deadline = now + wait_time,
+                // Keep to the schedule, so any frames that are late are shown straight away
+                CatchUp::Burst => deadline += wait_time,
+                CatchUp::Skip => {
+                    deadline += wait_time;
+                    while deadline <= now && !wait_time.is_zero() {
+                        deadline += wait_time;
+                        missed += 1;
+                    }
+                }
+            }
+        }
+
+        // Move past the frames that were missed without showing them, unless one of them is
+        // where the marquee stops
+        marquee.set_outdated(
+            options
+                .stale_after
+                .is_some_and(|after| last_input.elapsed() >= after),
+        );
+        let first = placeholder.as_ref().filter(|_| marquee.is_empty());
+        let next_frame = |marquee: &mut Marquee| match first {
+            Some(text) => Ok(Some(marquee.placeholder(text))),
+            None => marquee.frame(),
+        };
+        let mut frame = next_frame(&mut marquee);
+        for _ in 0..missed {
+            match frame {
+                Ok(Some(ref f)) if stop(f).is_none() => {
+                    stats.dropped += 1;
+                    frame = next_frame(&mut marquee);
+                }
+                _ => break,
+            }
+        }
+        stats.parse_errors = marquee.parse_errors();
+
+        let mut frame = match frame {
+            Ok(Some(frame)) => frame,
+            Ok(None) => continue,
+            Err(err) => break Err(err),
+        };
+        // The message can change the time until the next frame (`speed_start` and `speed_end` in
+        // the JSON), which isn't possible when the frames come from a clock
+        if let Some(delay) = frame.delay {
+            if sync.is_none() && options.clock != Clock::External {
+                deadline = deadline + delay - wait_time;
+            }
+        }
+        if let (Some(cmd), true) = (&options.filter_cmd, options.filter_frames) {
+            match filter::filter(cmd, &frame.rows.join("\n")) {
+                Ok(Some(filtered)) => frame.rows = filtered.split('\n').map(String::from).collect(),
+                Ok(None) => {}
+                Err(err) => break Err(Error::Filter(err)),
+            }
+        }
+
+        if let Err(err) = printer.print(&frame) {
+            break Err(Error::Output(err));
+        }
+        if let Some(Err(err)) = recorder.as_mut().map(|r| r.record(&frame)) {
+            break Err(Error::Record(err));
+        }
+        if let Some(Err(err)) = publisher.as_mut().map(|p| p.publish(&frame)) {
+            break Err(Error::Publish(err));
+        }
+        // The placeholder isn't a message, so it isn't passed on
+        if let (Some(notifier), None) = (&mut notifier, first) {
+            notifier.notify(&options, &frame.message);
+        }
+        if let (Some(on_change), None) = (&mut on_change, first) {
+            on_change.run(&options, &frame.message);
+        }
+        if let (Some(cmd), true) = (&options.on_cycle, frame.cycle_end) {
+            hook::on_cycle(&options, cmd, &frame.message);
+        }
+        if let (Some(source), None) = (&mut source, first) {
+            source.frame(&frame);
+        }
+        stats.frames += 1;
+        stats.frame_time += now.elapsed();
+        nudge = fastrand::f64() * 2.0 - 1.0;
+
+        if let Some(exit) = stop(&frame) {
+            break Ok(exit);
+        }
+    };
+
+    // Move off of the marquee line even if something went wrong, so that the terminal is left
+    // how it was (unless `--on-finish` says otherwise, which is only once `--no-loop` is done)
+    let finished = match (&result, &options.on_finish) {
+        (Ok(Exit::Finished), OnFinish::Hold) => Ok(()),
+        (Ok(Exit::Finished), OnFinish::Clear) => printer.clear(),
+        _ => printer.finish(),
+    }
+    .map_err(Error::Output);
+    if let (Ok(Exit::Finished), OnFinish::Exec(cmd)) = (&result, &options.on_finish) {
+        match source::shell(cmd).status() {
+            Ok(status) if !status.success() => {
+                log::warn!("The --on-finish command failed ({})", status)
+            }
+            Ok(_) => {}
+            Err(err) => log::warn!("Failed to run the --on-finish command: {}", err),
+        }
+    }
+    if options.stats {
+        eprint!("{}", stats);
+    }
+    result.and_then(|exit| finished.map(|()| exit))
+}
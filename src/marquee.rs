@@ -0,0 +1,1496 @@
+//! Working out what each frame of the marquee looks like
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::Error,
+    figlet::{braille, Font},
+    style::{highlight, log_level, JsonStyle, Style},
+    text::{
+        format_age, mirror, pad, progress_bar, sanitize, smooth_edges, spinner_frames,
+        strip_escapes, title_case, truncate, visual_order, word_starts, wrap, SMOOTH_STEPS,
+    },
+    width::{Widths, WIDE_TAIL},
+    AlertEffect, Align, Cli, Color, Direction, Mode, OnHidden, ScrollUnit, SeparatorOrder,
+    StaleStyle,
+};
+
+/// A function which returns true (for serde default)
+fn default_true() -> bool {
+    true
+}
+
+/// A function which returns 1 (for serde default)
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JsonInput {
+    /// The prefix to put before the content
+    #[serde(default)]
+    pub prefix: String,
+
+    /// The actual content to rotate
+    pub content: String,
+
+    /// The suffix to put after the content
+    #[serde(default)]
+    pub suffix: String,
+
+    /// If the line should rotate
+    #[serde(default = "default_true")]
+    pub rotate: bool,
+
+    /// How many times the line is shown each round compared to the other lines when using
+    /// `--rotate-all`
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+
+    /// How many full cycles the line is shown for when using `--rotate-all` (overrides `--repeat`)
+    #[serde(default)]
+    pub repeat: Option<usize>,
+
+    /// If the prefix is moved along with the content (overrides `--rotate-prefix`)
+    #[serde(default)]
+    pub rotate_prefix: Option<bool>,
+
+    /// If the suffix is moved along with the content (overrides `--rotate-suffix`)
+    #[serde(default)]
+    pub rotate_suffix: Option<bool>,
+
+    /// Where to put the content if it fits in the output (overrides `--align`)
+    #[serde(default)]
+    pub align: Option<Align>,
+
+    /// The character to pad the content with (overrides `--pad-char`)
+    #[serde(default)]
+    pub pad: Option<char>,
+
+    /// How far along a job is, which is shown as a progress bar after the content (0-100)
+    #[serde(default)]
+    pub percent: Option<f64>,
+
+    /// How many characters into the content to start from (overrides `--start-offset`)
+    #[serde(default)]
+    pub start_offset: Option<usize>,
+
+    /// The milliseconds between each frame during the first cycle of the line (overrides
+    /// `--delay`)
+    #[serde(default)]
+    pub speed_start: Option<u64>,
+
+    /// The milliseconds between each frame once the line has been shown for `speed_ramp` cycles
+    /// (overrides `--delay`)
+    #[serde(default)]
+    pub speed_end: Option<u64>,
+
+    /// How many cycles it takes to go from `speed_start` to `speed_end`
+    #[serde(default)]
+    pub speed_ramp: Option<usize>,
+
+    /// If the line is shown, a hidden line is shown as blank space or not at all (see
+    /// `--on-hidden`)
+    #[serde(default = "default_true")]
+    pub visible: bool,
+
+    /// The text attributes of the content (overrides `--bold`, `--dim`, etc.)
+    #[serde(flatten)]
+    pub style: JsonStyle,
+}
+
+/// Only the `visible` field of the JSON, so that hidden lines can be found without needing the
+/// rest of the line to be valid
+#[derive(Deserialize)]
+struct Visibility {
+    #[serde(default = "default_true")]
+    visible: bool,
+}
+
+/// The content of `message`, without the rest of the JSON that it came in with `--json`
+pub fn content_of(options: &Cli, message: &str) -> String {
+    match options
+        .json
+        .then(|| serde_json::from_str::<JsonInput>(message).ok())
+        .flatten()
+    {
+        Some(json) => json.content,
+        None => message.to_string(),
+    }
+}
+
+/// The delay between each frame during the `loops`th cycle of the line, which moves evenly from
+/// `speed_start` to `speed_end`.  This is `None` if the line doesn't change the speed.
+fn ramp_delay(options: &Cli, json: &JsonInput, loops: usize) -> Option<Duration> {
+    if json.speed_start.is_none() && json.speed_end.is_none() {
+        return None;
+    }
+    let start = json.speed_start.unwrap_or(options.delay) as f64;
+    let end = json.speed_end.unwrap_or(options.delay) as f64;
+    let ramp = json.speed_ramp.unwrap_or(1).max(1);
+    let along = loops.min(ramp) as f64 / ramp as f64;
+    let millis = start + (end - start) * along;
+    Some(options.delay_for(Duration::from_secs_f64(millis / 1000.0)))
+}
+
+/// If the prefix and suffix are moved along with the content rather than staying where they are
+/// (see `--rotate-prefix` and `--rotate-suffix`)
+fn rotate_decoration(options: &Cli, json: Option<&JsonInput>) -> (bool, bool) {
+    (
+        json.and_then(|j| j.rotate_prefix)
+            .unwrap_or(options.rotate_prefix),
+        json.and_then(|j| j.rotate_suffix)
+            .unwrap_or(options.rotate_suffix),
+    )
+}
+
+/// Build the order in which the messages are shown for one round of `--rotate-all`.
+///
+/// Each message is shown `weight` times, spread out as evenly as possible across the round (this
+/// is the same "smooth weighted round-robin" that nginx uses for its upstreams).
+fn build_round(weights: &[u32]) -> VecDeque<usize> {
+    let weights: Vec<i64> = weights.iter().map(|&w| w.max(1) as i64).collect();
+    let total: i64 = weights.iter().sum();
+    let mut current = vec![0; weights.len()];
+    let mut round = VecDeque::with_capacity(total as usize);
+    for _ in 0..total {
+        for (c, w) in current.iter_mut().zip(&weights) {
+            *c += w;
+        }
+        let (best, _) = current
+            .iter()
+            .enumerate()
+            .max_by_key(|&(i, c)| (*c, std::cmp::Reverse(i)))
+            .expect("there is always at least one message");
+        current[best] -= total;
+        round.push_back(best);
+    }
+    round
+}
+
+/// A single frame of output
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// The lines to print, this only has more than one row when using `--rows`
+    pub rows: Vec<String>,
+    /// The message that the frame was worked out from
+    pub message: Rc<str>,
+    /// If the content is moving
+    pub rotating: bool,
+    /// If this is the last frame of a single pass (`--no-loop`)
+    pub finished: bool,
+    /// If this is the last frame of a cycle of the content
+    pub cycle_end: bool,
+    /// How long to wait before the next frame, if the message changes it from `--delay` (see
+    /// `speed_start` and `speed_end` in the JSON)
+    pub delay: Option<Duration>,
+    /// If the message matches `--alert-on`
+    pub alert: bool,
+}
+
+/// The state of the marquee, this holds the messages that can be shown and works out each frame
+#[derive(Debug)]
+pub struct Marquee {
+    options: Cli,
+    /// Right-to-left text moves the opposite way, so this is `--reverse` flipped for it
+    reverse: bool,
+
+    /// The messages that can be shown, this only holds the latest line unless `--rotate-all` is
+    /// set
+    messages: Vec<Rc<str>>,
+    /// When each of the messages came in, this is used for `{age}` (which is `0s` when it isn't
+    /// known)
+    received: Vec<Option<Instant>>,
+    /// The index of the message that is currently being shown, how many frames of the current
+    /// cycle and how many cycles it has been shown for, and the messages left to show this round,
+    /// this is used for `--rotate-all`
+    current: usize,
+    frames: usize,
+    cycles: usize,
+    round: VecDeque<usize>,
+    /// The number of cycles that the message has been shown for since it was picked, this is
+    /// used for `speed_start` and `speed_end`
+    loops: usize,
+    /// If the next frame should move onto the next message
+    next: bool,
+    /// The number of messages that weren't valid JSON
+    parse_errors: usize,
+
+    /// The message that `json` and `content` were worked out from (which may be out of date)
+    prepared: Option<Rc<str>>,
+    json: Option<JsonInput>,
+    content: String,
+    /// If the content matches `--alert-on`
+    alert: bool,
+    /// The color of the log level in the content (`--loglevel-colors`)
+    level: Option<Color>,
+    /// If no input has come in for a while (`--stale-after`)
+    outdated: bool,
+
+    i: usize,
+    /// The previous width, this is used for knowing when to reset `i`
+    prev_width: usize,
+    /// The separator that is being used, and its index in `--separator-list`
+    separator: String,
+    separator_index: usize,
+    /// If anything that is worked out from the content has to be redone on the next frame, even
+    /// if the content hasn't changed (i.e. when the separator has changed)
+    stale: bool,
+    /// The frame of the shared clock that is being shown (`--sync-group`)
+    tick: Option<usize>,
+    /// The number of frames that have been worked out, this is used for `--spinner` and
+    /// `--alert-effect flash`
+    spinner: usize,
+    /// How far the content is between two steps, in eighths of a character (`--smooth`)
+    sub: usize,
+    /// The font to draw the content with (`--figlet`)
+    font: Option<Font>,
+    /// How wide each character is in the terminal (`--probe-widths`), this isn't used with a font
+    widths: Option<Widths>,
+    // Things that are worked out from the content when it changes (see `changed` in `frame`),
+    // there is a row of `looped` for each row of the drawing with `--figlet`
+    looped: Vec<String>,
+    bounds: Vec<Vec<usize>>,
+    starts: Vec<usize>,
+    /// The color of each character of `looped` from `--highlight`
+    colors: Vec<Option<Color>>,
+    wrapped: Vec<String>,
+    /// The marquee in the right pane, which has its own messages (`--right`)
+    right: Option<Box<Marquee>>,
+}
+
+impl Marquee {
+    pub fn new(mut options: Cli, font: Option<Font>) -> Self {
+        // Both panes are padded to their width, so that the right one always starts in the same
+        // place
+        let right = options.right.is_some().then(|| {
+            options.align.get_or_insert(Align::Left);
+            let mut right = options.clone();
+            right.width = options.right_width.unwrap_or(options.width);
+            right.total_width = None;
+            right.prefix = None;
+            right.suffix = None;
+            right.spinner = None;
+            right.right = None;
+            Box::new(Marquee::new(right, None))
+        });
+        let separator = options
+            .separator_list
+            .first()
+            .unwrap_or(&options.separator)
+            .clone();
+        Self {
+            reverse: options.reverse != (options.direction == Direction::Rtl),
+            options,
+            messages: Vec::new(),
+            received: Vec::new(),
+            current: 0,
+            frames: 0,
+            cycles: 0,
+            round: VecDeque::new(),
+            loops: 0,
+            next: true,
+            parse_errors: 0,
+            prepared: None,
+            json: None,
+            content: String::new(),
+            alert: false,
+            level: None,
+            outdated: false,
+            i: 0,
+            prev_width: 0,
+            separator,
+            separator_index: 0,
+            stale: false,
+            tick: None,
+            spinner: 0,
+            sub: 0,
+            font,
+            widths: None,
+            looped: Vec::new(),
+            bounds: Vec::new(),
+            starts: Vec::new(),
+            colors: Vec::new(),
+            wrapped: Vec::new(),
+            right,
+        }
+    }
+
+    /// Replace `--prefix` (see `--prefix-file`)
+    pub fn set_prefix(&mut self, prefix: String) {
+        self.options.prefix = Some(prefix);
+        self.restyle();
+    }
+
+    /// Replace `--suffix` (see `--suffix-file`)
+    pub fn set_suffix(&mut self, suffix: String) {
+        self.options.suffix = Some(suffix);
+        self.restyle();
+    }
+
+    /// Work out the content again if the prefix or suffix is part of it (`--rotate-prefix`)
+    fn restyle(&mut self) {
+        let json = self.json.as_ref();
+        if rotate_decoration(&self.options, json) != (false, false) {
+            self.prepared = None;
+        }
+    }
+
+    /// Add a line of input to the right pane (see `push` and `--right`)
+    pub fn push_right(&mut self, line: String, received: Option<Instant>) -> bool {
+        self.right
+            .as_mut()
+            .is_some_and(|right| right.push(line, received))
+    }
+
+    /// Add a line of input, this replaces the current message unless `--rotate-all` is set.
+    ///
+    /// `received` is when the line came in, which is passed in rather than read from the clock
+    /// here so that the frames only depend on what they are given (and the clock isn't there on
+    /// `wasm32`).
+    ///
+    /// Returns true if the message that is being shown may have changed, so the next frame should
+    /// be shown straight away.
+    pub fn push(&mut self, line: String, received: Option<Instant>) -> bool {
+        let hidden = self.options.json
+            && serde_json::from_str::<Visibility>(&line).is_ok_and(|v| !v.visible);
+        if hidden && (self.options.rotate_all || self.options.on_hidden == OnHidden::Previous) {
+            log::debug!("Ignoring hidden message: {:?}", line);
+            false
+        } else if line.is_empty() {
+            // An empty line resets what is being shown
+            log::info!("Clearing the messages");
+            self.messages.clear();
+            self.received.clear();
+            true
+        } else if !self.options.rotate_all {
+            log::info!("New message: {:?}", line);
+            self.messages = vec![Rc::from(line)];
+            self.received = vec![received];
+            true
+        } else if !self.messages.iter().any(|m| **m == *line) {
+            log::info!("Adding message: {:?}", line);
+            self.messages.push(Rc::from(line));
+            self.received.push(received);
+            self.messages.len() == 1
+        } else {
+            log::debug!("Ignoring duplicate message: {:?}", line);
+            false
+        }
+    }
+
+    /// The frame that is shown before the first message comes in (see `--placeholder`), this
+    /// doesn't move but the spinner does
+    pub fn placeholder(&mut self, text: &Rc<str>) -> Frame {
+        let options = &self.options;
+        let spinner = options.spinner.map(|s| {
+            let frames = spinner_frames(s);
+            format!("{} ", frames[self.spinner % frames.len()])
+        });
+        self.spinner += 1;
+
+        let style = Style {
+            dim: true,
+            ..Style::default()
+        };
+        let row = format!(
+            "{}{}{}{}",
+            spinner.unwrap_or_default(),
+            options.prefix.as_deref().unwrap_or_default(),
+            style.apply(text),
+            options.suffix.as_deref().unwrap_or_default()
+        )
+        // There isn't a message yet, so it doesn't have an age
+        .replace("{age}", "");
+        Frame {
+            rows: vec![row],
+            message: Rc::clone(text),
+            rotating: false,
+            finished: false,
+            cycle_end: false,
+            delay: None,
+            alert: false,
+        }
+    }
+
+    /// Set if the content is stale, so it is shown with `--stale-style` and `--stale-suffix`
+    pub fn set_outdated(&mut self, outdated: bool) {
+        if outdated != self.outdated {
+            log::info!(
+                "The content is {}",
+                if outdated { "stale" } else { "fresh" }
+            );
+            self.outdated = outdated;
+        }
+    }
+
+    /// Skip the rest of the current message and move onto the next one
+    pub fn skip(&mut self) {
+        self.frames = 0;
+        self.cycles = 0;
+        self.next = true;
+    }
+
+    /// If there are no messages to show (in either pane)
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty() && self.right.as_ref().is_none_or(|right| right.is_empty())
+    }
+
+    /// The number of messages that have been ignored because they weren't valid JSON
+    pub fn parse_errors(&self) -> usize {
+        self.parse_errors + self.right.as_ref().map_or(0, |right| right.parse_errors())
+    }
+
+    /// Use the widths that were measured in the terminal, instead of every character being one
+    /// column wide (see `--probe-widths`)
+    pub fn set_widths(&mut self, widths: Widths) {
+        self.widths = Some(widths);
+        self.stale = true;
+        if let Some(right) = &mut self.right {
+            right.set_widths(widths);
+        }
+    }
+
+    /// If `c` is a column of its own, rather than a combining mark that is drawn in the one before
+    /// it (which is only known with `--probe-widths`)
+    fn starts_column(&self, c: char) -> bool {
+        match (&self.font, &self.widths) {
+            (None, Some(widths)) => widths.starts_column(c),
+            _ => true,
+        }
+    }
+
+    /// Set the frame of the shared clock that the next frame is for (see `--sync-group`), new
+    /// content starts from where it would be if it had been shown since the clock started
+    pub fn sync(&mut self, tick: usize) {
+        self.tick = Some(tick);
+        if let Some(right) = &mut self.right {
+            right.sync(tick);
+        }
+    }
+
+    /// Start `frame` frames into the content, as if it had been shown since the first one (see
+    /// `render_frame`)
+    pub fn seek(&mut self, frame: usize) {
+        self.sync(frame);
+        self.spinner = frame;
+    }
+
+    /// Remove every message other than the one that is being shown, so that it is the only one
+    /// left with `--rotate-all`
+    pub fn flush(&mut self) {
+        if let Some(current) = self.messages.get(self.current).cloned() {
+            log::info!("Flushing the other messages");
+            self.messages = vec![current];
+            self.received = vec![self.received[self.current]];
+            self.current = 0;
+        }
+        self.round.clear();
+    }
+
+    /// Use `delay` (in milliseconds) as `--delay` from now on, which is where a speed ramp starts
+    /// or ends when the message doesn't say (see `speed_start` and `speed_end`)
+    pub fn set_delay(&mut self, delay: u64) {
+        self.options.delay = delay;
+    }
+
+    /// Use `separator` between each loop of the content from now on
+    pub fn set_separator(&mut self, separator: String) {
+        self.separator = separator;
+        self.stale = true;
+        // The content starts again from the beginning, so the cycle does as well (otherwise a
+        // shorter separator could put it past the end of the cycle)
+        self.frames = 0;
+    }
+
+    /// Move onto the next separator from `--separator-list`, this is done at the end of each cycle
+    fn next_separator(&mut self) {
+        let list = &self.options.separator_list;
+        if list.len() < 2 {
+            return;
+        }
+        self.separator_index = match self.options.separator_order {
+            SeparatorOrder::Sequential => (self.separator_index + 1) % list.len(),
+            SeparatorOrder::Random => fastrand::usize(..list.len()),
+        };
+        self.separator.clone_from(&list[self.separator_index]);
+        self.stale = true;
+    }
+
+    /// Work out the next frame, this returns `None` if there is nothing to show
+    pub fn frame(&mut self) -> Result<Option<Frame>, Error> {
+        let left = self.pane_frame()?;
+        let Some(right) = &mut self.right else {
+            return Ok(left);
+        };
+        let right = right.pane_frame()?;
+
+        // A pane that has nothing to show is left blank, so the other one stays where it is
+        let options = &self.options;
+        let left_blank = " ".repeat(options.total_width.unwrap_or(options.width));
+        let right_blank = " ".repeat(options.right_width.unwrap_or(options.width));
+        let row = |frame: &Option<Frame>, r: usize, blank: &str| {
+            frame
+                .as_ref()
+                .and_then(|f| f.rows.get(r))
+                .map_or(blank, |row| row)
+                .to_string()
+        };
+        let count = |frame: &Option<Frame>| frame.as_ref().map_or(0, |f| f.rows.len());
+        let rows = (0..count(&left).max(count(&right)))
+            .map(|r| {
+                format!(
+                    "{}{}{}",
+                    row(&left, r, &left_blank),
+                    options.pane_separator,
+                    row(&right, r, &right_blank)
+                )
+            })
+            .collect();
+
+        let rotating = [&left, &right]
+            .iter()
+            .any(|f| f.as_ref().is_some_and(|f| f.rotating));
+        let finished = [&left, &right]
+            .iter()
+            .all(|f| f.as_ref().is_none_or(|f| f.finished));
+        // The rest of the frame is from the left pane, unless it has nothing to show
+        Ok(left.or(right).map(|frame| Frame {
+            rows,
+            rotating,
+            finished,
+            ..frame
+        }))
+    }
+
+    /// Work out the next frame of this pane, without the right pane
+    fn pane_frame(&mut self) -> Result<Option<Frame>, Error> {
+        let options = &self.options;
+        if self.messages.is_empty() {
+            return Ok(None);
+        }
+
+        // Pick the next message to show if this one is done (or it no longer exists)
+        if self.next || self.current >= self.messages.len() {
+            self.next = false;
+            self.current = loop {
+                match self.round.pop_front() {
+                    Some(i) if i < self.messages.len() => {
+                        log::debug!("Switching to message {}", i);
+                        break i;
+                    }
+                    Some(_) => continue,
+                    None => {
+                        let weights: Vec<u32> = self
+                            .messages
+                            .iter()
+                            .map(|m| {
+                                options
+                                    .json
+                                    .then(|| serde_json::from_str::<JsonInput>(m).ok())
+                                    .flatten()
+                                    .map_or(1, |j| j.weight)
+                            })
+                            .collect();
+                        self.round = build_round(&weights);
+                        if options.shuffle {
+                            fastrand::shuffle(self.round.make_contiguous());
+                        }
+                        log::trace!("Starting a new round: {:?}", self.round);
+                    }
+                }
+            };
+        }
+
+        // This only clones the `Rc`, not the message itself
+        let msg = Rc::clone(&self.messages[self.current]);
+
+        // The content is only worked out when the message is new, rather than every frame
+        let mut content_changed = false;
+        let mut preview = false;
+        if !self.prepared.as_ref().is_some_and(|p| Rc::ptr_eq(p, &msg)) {
+            // If `--json`, then parse the json
+            self.json = match options
+                .json
+                .then(|| serde_json::from_str::<JsonInput>(&msg))
+            {
+                Some(Err(err)) if options.strict => return Err(Error::Json(err)),
+                Some(Err(err)) => {
+                    self.parse_errors += 1;
+                    log::warn!("Failed to parse JSON, ignoring the message: {}", err);
+                    // Remove the message because there's no reason to keep trying to parse the
+                    // json
+                    self.messages.remove(self.current);
+                    self.received.remove(self.current);
+                    return Ok(None);
+                }
+                Some(Ok(json)) => Some(json),
+                None => None,
+            };
+            self.prepared = Some(Rc::clone(&msg));
+            self.loops = 0;
+
+            // If there is json, grab the string
+            let mut out = match &self.json {
+                Some(JsonInput { content, .. }) => content.clone(),
+                None => msg.to_string(),
+            };
+            for replace in &options.replace {
+                out = replace.apply(&out);
+            }
+            if options.upper {
+                out = out.to_uppercase();
+            } else if options.lower {
+                out = out.to_lowercase();
+            } else if options.title {
+                out = title_case(&out);
+            }
+
+            // The prefix and suffix are put in the same order as when they aren't moving
+            let json = self.json.as_ref();
+            let (rotate_prefix, rotate_suffix) = rotate_decoration(options, json);
+            if rotate_prefix {
+                out = format!(
+                    "{}{}{}",
+                    json.map_or("", |j| &j.prefix),
+                    options.prefix.as_deref().unwrap_or_default(),
+                    out
+                );
+            }
+            if rotate_suffix {
+                out = format!(
+                    "{}{}{}",
+                    out,
+                    json.map_or("", |j| &j.suffix),
+                    options.suffix.as_deref().unwrap_or_default()
+                );
+            }
+            out = sanitize(&out, options.tab_width, options.control_chars);
+            out = visual_order(&out, options.direction);
+
+            self.alert = options.alert_on.as_ref().is_some_and(|r| r.is_match(&out));
+            self.level = options.loglevel_colors.then(|| log_level(&out)).flatten();
+            content_changed = out != self.content;
+            self.content = out;
+            preview = options.preview.is_some();
+        }
+        let json = &self.json;
+        let content = &self.content;
+        let (rotate_prefix, rotate_suffix) = rotate_decoration(options, json.as_ref());
+        let reverse = self.reverse;
+        // `{age}` is how long ago the message came in, which changes without the content changing
+        let age = format_age(self.received[self.current].map_or(Duration::ZERO, |at| at.elapsed()));
+        let prefix = options.prefix.as_ref().map(|p| p.replace("{age}", &age));
+        let suffix = options.suffix.as_ref().map(|s| s.replace("{age}", &age));
+
+        let mut style = match json {
+            Some(json) => Style::from_options(options).with(&json.style),
+            None => Style::from_options(options),
+        };
+        if let Some(color) = self.level {
+            style.color = Some(color);
+        }
+        if self.alert {
+            match options.alert_effect {
+                AlertEffect::Flash if self.spinner.is_multiple_of(2) => {
+                    style.color = Some(options.alert_color)
+                }
+                AlertEffect::Flash | AlertEffect::Bell => {}
+                AlertEffect::Invert => style.reverse = true,
+                AlertEffect::Color => style.color = Some(options.alert_color),
+            }
+        }
+        let outdated = self.outdated;
+        if outdated {
+            match options.stale_style {
+                StaleStyle::Dim => style.dim = true,
+                StaleStyle::Italic => style.italic = true,
+                StaleStyle::Plain => {}
+            }
+        }
+
+        // A hidden line is blank space the same width as the output (`--on-hidden blank`)
+        if json.as_ref().is_some_and(|j| !j.visible) {
+            let width = options.total_width.unwrap_or(options.width);
+            return Ok(Some(Frame {
+                rows: vec![" ".repeat(width); options.rows],
+                message: msg,
+                rotating: false,
+                finished: true,
+                cycle_end: true,
+                delay: None,
+                alert: self.alert,
+            }));
+        }
+
+        // Show the whole of a new message once before it starts moving (`--preview`)
+        if preview {
+            // The change is picked up on the next frame instead
+            self.stale |= content_changed;
+            let mut out = style.apply(content);
+            if !rotate_prefix {
+                out = format!(
+                    "{}{}{}",
+                    json.as_ref().map_or("", |j| &j.prefix),
+                    prefix.as_deref().unwrap_or_default(),
+                    out
+                );
+            }
+            if !rotate_suffix {
+                out = format!(
+                    "{}{}{}",
+                    out,
+                    json.as_ref().map_or("", |j| &j.suffix),
+                    suffix.as_deref().unwrap_or_default()
+                );
+            }
+            return Ok(Some(Frame {
+                rows: vec![out],
+                message: msg,
+                rotating: false,
+                finished: false,
+                cycle_end: false,
+                delay: options.preview.map(Duration::from_millis),
+                alert: self.alert,
+            }));
+        }
+
+        // The frame of the spinner (`--spinner`), which moves on every frame
+        let spinner = options.spinner.map(|s| {
+            let frames = spinner_frames(s);
+            format!("{} ", frames[self.spinner % frames.len()])
+        });
+        self.spinner += 1;
+
+        // The progress bar goes between the content and the suffix
+        let bar = json.as_ref().and_then(|j| j.percent).map(|percent| {
+            let (filled, empty) = options.progress_chars;
+            progress_bar(percent, options.progress_width, filled, empty)
+        });
+
+        // The width of the moving content, when `--total-width` is used this is whatever is left
+        // after the prefix and suffix
+        let width = match options.total_width {
+            Some(total) => {
+                let decoration = [
+                    spinner.as_deref(),
+                    bar.as_deref(),
+                    prefix.as_deref().filter(|_| !rotate_prefix),
+                    json.as_ref()
+                        .map(|j| j.prefix.as_str())
+                        .filter(|_| !rotate_prefix),
+                    json.as_ref()
+                        .map(|j| j.suffix.as_str())
+                        .filter(|_| !rotate_suffix),
+                    suffix.as_deref().filter(|_| !rotate_suffix),
+                    options.stale_suffix.as_deref().filter(|_| outdated),
+                ];
+                let len: usize = decoration.iter().flatten().map(|d| d.chars().count()).sum();
+                total.saturating_sub(len)
+            }
+            None => options.width,
+        };
+
+        // If the string (or the space for it) has changed, then anything that is worked out from
+        // it needs to be redone
+        let changed = content_changed || self.prev_width != width || self.stale;
+        self.prev_width = width;
+        self.stale = false;
+
+        // Only change `i` if this single string will be rotated, which is only true if the input
+        // is too long to be shown all at once and json.rotate is true
+        let should_rotate = options.mode == Mode::Scroll && json.as_ref().is_none_or(|j| j.rotate);
+        let start_offset = json
+            .as_ref()
+            .and_then(|j| j.start_offset)
+            .unwrap_or(options.start_offset);
+        let rotating;
+        // The value of `i` for the last frame of a single pass (`--no-loop`), this is when the end
+        // (or start if reversed) of the content hits the edge of the output.  `--by words` can skip
+        // over this, so anything past it is also the last frame.
+        let last;
+        let shown;
+        // The amount of frames that it takes for the content to repeat itself
+        let cycle;
+        // If the frame is part of the way between two steps (`--smooth`)
+        let mut partial = false;
+        let mut rows;
+
+        if options.rows > 1 {
+            // The content is wrapped onto multiple rows, and then the rows are moved upwards
+            // (rather than moving the characters to the left)
+            if changed {
+                self.wrapped = wrap(content, width);
+                self.i = if !reverse {
+                    0
+                } else {
+                    self.wrapped.len().saturating_sub(options.rows)
+                };
+            }
+            let wrapped = &self.wrapped;
+            // Leave a blank row between the end of the content and the start of it again
+            let period = wrapped.len() + 1;
+            // Start `--start-offset` rows in
+            if changed && start_offset > 0 {
+                self.frames = start_offset % period;
+                self.i = if reverse {
+                    (self.i + period - self.frames) % period
+                } else {
+                    (self.i + self.frames) % period
+                };
+            }
+
+            rotating = wrapped.len() > options.rows && should_rotate;
+            last = if !reverse {
+                wrapped.len().saturating_sub(options.rows)
+            } else {
+                0
+            };
+            shown = self.i;
+            cycle = period;
+
+            rows = (0..options.rows.min(wrapped.len()))
+                .map(|r| {
+                    wrapped
+                        .get((self.i + r) % period)
+                        .cloned()
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>();
+
+            // Show that there is more content after the last row
+            if options.mode == Mode::Truncate && wrapped.len() > options.rows {
+                let row = rows.last_mut().expect("there is at least one row");
+                let keep = width.saturating_sub(options.ellipsis.chars().count());
+                *row = row
+                    .chars()
+                    .take(keep)
+                    .chain(options.ellipsis.chars())
+                    .take(width)
+                    .collect();
+            }
+
+            if rotating {
+                self.i = if reverse {
+                    (self.i + period - 1) % period
+                } else {
+                    (self.i + 1) % period
+                };
+            }
+        } else {
+            // With `--figlet` each character is drawn over several rows, so everything is worked
+            // out in columns of the drawing rather than in characters
+            let measure = |s: &str| match (&self.font, &self.widths) {
+                (Some(font), _) => font.width(s),
+                (None, Some(widths)) => widths.width(s),
+                (None, None) => s.chars().count(),
+            };
+            // With `--braille` there are two dots (columns of the drawing) in each character
+            let width = if options.braille { width * 2 } else { width };
+            // With `--seamless` there is no separator, and content that fits is repeated until it
+            // doesn't so that it still moves
+            let separator = if options.seamless {
+                ""
+            } else {
+                &self.separator
+            };
+            let tiled;
+            let content = match measure(content) {
+                len if options.seamless && len > 0 && len <= width => {
+                    tiled = content.repeat(width / len + 1);
+                    &tiled
+                }
+                _ => content,
+            };
+            let raw_len = measure(content);
+            // The amount of columns before the content repeats itself
+            let period = raw_len + measure(separator);
+            let content_start = if reverse { period - raw_len } else { 0 };
+
+            // If the string has changed, then reset `i`.  When reversed, we start with the end of
+            // the content at the right of the output.
+            if changed {
+                self.i = if !reverse {
+                    0
+                } else {
+                    period.saturating_sub(width)
+                };
+
+                // Put the separator at the beginning/end depending on whether --reverse is set.
+                // This is only done when the content changes, along with finding where each
+                // character starts, so that each frame is only a slice of it (or two slices when
+                // it wraps around to the start).
+                let looped = if reverse {
+                    format!("{}{}", separator, content)
+                } else {
+                    format!("{}{}", content, separator)
+                };
+                // The highlights are found before the wide characters are split up, so that they
+                // can still match them
+                self.colors = match &self.font {
+                    Some(_) => Vec::new(),
+                    None => highlight(&options.highlight, &looped),
+                };
+                // Each wide character is followed by `WIDE_TAIL`, so that each character of
+                // `looped` is a column
+                let (looped, content_len) = match (&self.font, &self.widths) {
+                    (None, Some(widths)) => {
+                        self.colors = looped
+                            .chars()
+                            .zip(&self.colors)
+                            .flat_map(|(c, &color)| vec![color; widths.of(c)])
+                            .collect();
+                        (widths.cells(&looped), raw_len)
+                    }
+                    _ => (looped, content.chars().count()),
+                };
+                // Combining marks are drawn in the same column as the character before them, so
+                // they're kept with it rather than being a column of their own
+                let chars: Vec<char> = looped.chars().filter(|&c| self.starts_column(c)).collect();
+                let starts = word_starts(&chars, chars.len() - content_len);
+                match &self.font {
+                    Some(font) => {
+                        let (rows, columns) = font.render(&looped);
+                        self.starts = starts.into_iter().map(|s| columns[s]).collect();
+                        self.looped = rows;
+                    }
+                    None => {
+                        self.starts = starts;
+                        self.looped = vec![looped];
+                    }
+                }
+                self.bounds = self
+                    .looped
+                    .iter()
+                    .map(|row| {
+                        let mut bounds: Vec<usize> = row
+                            .char_indices()
+                            .filter(|&(_, c)| self.starts_column(c))
+                            .map(|(b, _)| b)
+                            .chain(std::iter::once(row.len()))
+                            .collect();
+                        // Anything before the first column is part of it
+                        bounds[0] = 0;
+                        bounds
+                    })
+                    .collect();
+            }
+
+            // With `--smooth`, each step is split into frames that are part of the way to the next
+            // one
+            let steps = if options.smooth && options.by == ScrollUnit::Chars {
+                SMOOTH_STEPS
+            } else {
+                1
+            };
+            if changed {
+                self.sub = 0;
+            }
+
+            rotating = raw_len > width && should_rotate;
+            last = if !reverse {
+                raw_len.saturating_sub(width)
+            } else {
+                period - raw_len
+            };
+            // The blank frames from `--gap-frames` are at the end of each cycle, once the content
+            // is back where it started
+            let gap_frames = if rotating { options.gap_frames } else { 0 };
+            cycle = period * steps + gap_frames;
+
+            // Start part of the way through the cycle, `--start-offset` characters in and at the
+            // same place as every other marquee in the group (`--sync-group`).  There is nothing to
+            // move through if the content and the separator are both empty (the cycle is 0 frames
+            // long).
+            let start = self.tick.unwrap_or(0) + start_offset * steps;
+            if changed
+                && cycle > 0
+                && (self.tick.is_some() || start > 0)
+                && options.by == ScrollUnit::Chars
+            {
+                self.frames = start % cycle;
+                let moved = self.frames.min(period * steps);
+                let chars = (moved / steps) % period;
+                self.sub = moved % steps;
+                self.i = if reverse {
+                    (self.i + period - chars) % period
+                } else {
+                    (self.i + chars) % period
+                };
+            }
+            shown = self.i;
+            let gap = self.frames >= period * steps;
+            partial = rotating && self.sub > 0 && !gap;
+
+            let count = self.looped.len();
+            rows = (0..count)
+                .map(|r| {
+                    if gap {
+                        return " ".repeat(width);
+                    }
+                    let (looped, bounds) = (&self.looped[r], &self.bounds[r]);
+                    if options.mode == Mode::Truncate {
+                        // Only the last row ends with the ellipsis, in the same way as `--rows`
+                        let ellipsis = if r + 1 == count && !options.braille {
+                            &options.ellipsis
+                        } else {
+                            ""
+                        };
+                        if raw_len <= width {
+                            return window(looped, bounds, content_start, raw_len, period);
+                        }
+                        // Cut the content in columns rather than characters (which aren't the
+                        // same with combining marks), and then add as much of the ellipsis as fits
+                        let keep = width.saturating_sub(ellipsis.chars().count());
+                        window(looped, bounds, content_start, keep, period)
+                            + &truncate(ellipsis, width - keep, "")
+                    } else if width < raw_len {
+                        window(looped, bounds, self.i, width, period)
+                    } else {
+                        window(looped, bounds, content_start, raw_len, period)
+                    }
+                })
+                .collect();
+            if let (None, Some(widths)) = (&self.font, &self.widths) {
+                for row in rows.iter_mut() {
+                    *row = widths.fit(row);
+                }
+            }
+
+            if partial {
+                for row in rows.iter_mut() {
+                    *row = smooth_edges(row, self.sub, reverse);
+                }
+            }
+            if options.braille {
+                rows = braille(&rows);
+            } else if !gap {
+                // Show where the content starts again and anything that `--highlight` matches,
+                // using where each character is in `looped`
+                let scrolling = options.mode == Mode::Scroll && width < raw_len;
+                let start = if scrolling { shown } else { content_start };
+                let separator = Style::separator(options);
+                let content = content_start..content_start + raw_len;
+                let style_at = |k: usize| {
+                    let i = (start + k) % period;
+                    if scrolling && !separator.is_plain() && !content.contains(&i) {
+                        return Some(separator);
+                    }
+                    self.colors.get(i).copied().flatten().map(|color| Style {
+                        color: Some(color),
+                        ..style
+                    })
+                };
+                if (scrolling && !separator.is_plain()) || self.colors.iter().any(Option::is_some) {
+                    for row in rows.iter_mut() {
+                        // The column that each character is in, since combining marks share the
+                        // column of the character before them
+                        let columns: Vec<usize> = row
+                            .chars()
+                            .scan(0, |next: &mut usize, c| {
+                                if self.starts_column(c) {
+                                    *next += 1;
+                                }
+                                Some(next.saturating_sub(1))
+                            })
+                            .collect();
+                        *row = Style::apply_each(row, &style, |k| style_at(columns[k]));
+                    }
+                }
+            }
+
+            if rotating && !gap {
+                self.sub = (self.sub + 1) % steps;
+            }
+            if rotating && self.sub == 0 && !gap {
+                let i = self.i;
+                self.i = match options.by {
+                    // Decrement, wrapping around to the end
+                    ScrollUnit::Chars if reverse => (i + period - 1) % period,
+                    ScrollUnit::Chars => (i + 1) % period,
+                    // Jump to the previous/next word, wrapping around at the ends
+                    ScrollUnit::Words => if reverse {
+                        self.starts
+                            .iter()
+                            .rev()
+                            .find(|&&s| s < i)
+                            .or(self.starts.last())
+                    } else {
+                        self.starts.iter().find(|&&s| s > i).or(self.starts.first())
+                    }
+                    .copied()
+                    .unwrap_or(0),
+                };
+            }
+        }
+
+        let align = json.as_ref().and_then(|j| j.align).or(options.align);
+        let pad_char = json.as_ref().and_then(|j| j.pad).or(options.pad_char);
+        for (r, out) in rows.iter_mut().enumerate() {
+            if align.is_some() || pad_char.is_some() || options.total_width.is_some() {
+                // `pad` counts characters, so combining marks are made up for
+                let marks = strip_escapes(out)
+                    .chars()
+                    .filter(|&c| !self.starts_column(c))
+                    .count();
+                *out = pad(
+                    out,
+                    width + marks,
+                    align.unwrap_or(Align::Left),
+                    pad_char.unwrap_or(' '),
+                );
+            }
+            // Wide characters have been counted as two columns until now
+            if self.widths.is_some() {
+                *out = out.replace(WIDE_TAIL, "");
+            }
+
+            if options.mirror {
+                *out += &mirror(&strip_escapes(out));
+            }
+
+            *out = style.apply(out);
+
+            // Add prefixes, unless they are already part of the content
+            if let Some(prefix) = prefix.as_ref().filter(|_| !rotate_prefix) {
+                *out = format!("{}{}", prefix, out);
+            }
+            if let Some(JsonInput { prefix, .. }) = json.as_ref().filter(|_| !rotate_prefix) {
+                *out = format!("{}{}", prefix, out);
+            }
+
+            // The progress bar is only on the first row, the rest are lined up with it
+            if let Some(bar) = &bar {
+                if r == 0 {
+                    *out += bar;
+                } else {
+                    *out += &" ".repeat(bar.chars().count());
+                }
+            }
+
+            // Add suffixes, unless they are already part of the content
+            if let Some(JsonInput { suffix, .. }) = json.as_ref().filter(|_| !rotate_suffix) {
+                *out += suffix;
+            }
+            if let Some(suffix) = suffix.as_ref().filter(|_| !rotate_suffix) {
+                *out += suffix;
+            }
+            if let Some(suffix) = options.stale_suffix.as_ref().filter(|_| outdated) {
+                *out += suffix;
+            }
+        }
+
+        // The spinner is only on the first row, the rest are lined up with it
+        if let Some(spinner) = spinner {
+            for (r, out) in rows.iter_mut().enumerate() {
+                let start = if r == 0 {
+                    spinner.clone()
+                } else {
+                    " ".repeat(spinner.chars().count())
+                };
+                out.insert_str(0, &start);
+            }
+        }
+
+        // This is everything when `--no-loop` is passed, if the string isn't moving, then printing
+        // it once is everything
+        let finished = if reverse {
+            shown <= last
+        } else {
+            shown >= last
+        };
+        let finished = !rotating || (finished && !partial);
+
+        // Move onto the next message once this one has been shown for enough cycles.  A cycle is
+        // the amount of frames that it takes to scroll through the content and separator once,
+        // even if the content isn't being rotated.
+        let repeat = json
+            .as_ref()
+            .and_then(|j| j.repeat)
+            .unwrap_or(options.repeat);
+        let delay = json
+            .as_ref()
+            .and_then(|j| ramp_delay(options, j, self.loops));
+        self.frames += 1;
+        let cycle_end = self.frames >= cycle;
+        if cycle_end {
+            self.frames = 0;
+            self.cycles += 1;
+            self.loops += 1;
+            self.next_separator();
+        }
+        if self.cycles >= repeat {
+            self.cycles = 0;
+            self.next = true;
+        }
+
+        Ok(Some(Frame {
+            rows,
+            message: msg,
+            rotating,
+            finished,
+            cycle_end,
+            delay,
+            alert: self.alert,
+        }))
+    }
+}
+
+/// Similar to `looped[start..start + len]`, but with characters (whose byte offsets are
+/// `bounds`) instead of bytes and wrapping around to the start.  The length is at most `period`,
+/// so it can only wrap around once.
+fn window(looped: &str, bounds: &[usize], start: usize, len: usize, period: usize) -> String {
+    let from = bounds[start];
+    let end = start + len;
+    if end <= period {
+        looped[from..bounds[end]].to_string()
+    } else {
+        let mut out = String::with_capacity(looped.len() - from + bounds[end - period]);
+        out += &looped[from..];
+        out += &looped[..bounds[end - period]];
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    /// A marquee with the options from `args`, in the same way as the `marquee` command
+    fn marquee(args: &[&str]) -> Marquee {
+        let options = Cli::try_parse_from(std::iter::once("marquee").chain(args.iter().copied()))
+            .expect("valid arguments");
+        let font = Font::from_options(&options).expect("the font loads");
+        Marquee::new(options, font)
+    }
+
+    /// The next `count` frames of `marquee`, with the rows joined by `\n`
+    fn frames(marquee: &mut Marquee, count: usize) -> Vec<String> {
+        (0..count)
+            .map(|_| {
+                marquee
+                    .frame()
+                    .expect("the frame is worked out")
+                    .map(|frame| frame.rows.join("\n"))
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// The byte offset of each character of `s`, and the end of it
+    fn bounds(s: &str) -> Vec<usize> {
+        s.char_indices()
+            .map(|(b, _)| b)
+            .chain(std::iter::once(s.len()))
+            .collect()
+    }
+
+    #[test]
+    fn window_without_wrapping() {
+        let looped = "abcdef|";
+        assert_eq!(window(looped, &bounds(looped), 0, 4, 7), "abcd");
+        assert_eq!(window(looped, &bounds(looped), 3, 4, 7), "def|");
+        assert_eq!(window(looped, &bounds(looped), 0, 7, 7), "abcdef|");
+    }
+
+    #[test]
+    fn window_wraps_around() {
+        let looped = "abcdef|";
+        assert_eq!(window(looped, &bounds(looped), 4, 4, 7), "ef|a");
+        assert_eq!(window(looped, &bounds(looped), 6, 4, 7), "|abc");
+        assert_eq!(window(looped, &bounds(looped), 6, 7, 7), "|abcdef");
+    }
+
+    #[test]
+    fn window_multibyte() {
+        let looped = "é漢😀b|";
+        assert_eq!(window(looped, &bounds(looped), 1, 3, 5), "漢😀b");
+        assert_eq!(window(looped, &bounds(looped), 3, 3, 5), "b|é");
+        assert_eq!(window(looped, &bounds(looped), 4, 2, 5), "|é");
+    }
+
+    #[test]
+    fn window_empty() {
+        assert_eq!(window("", &bounds(""), 0, 0, 0), "");
+    }
+
+    #[test]
+    fn frames_wrap_around_the_separator() {
+        let mut m = marquee(&["-w", "4", "-s", "|"]);
+        m.push("abcdef".into(), None);
+        assert_eq!(
+            frames(&mut m, 9),
+            ["abcd", "bcde", "cdef", "def|", "ef|a", "f|ab", "|abc", "abcd", "bcde"]
+        );
+    }
+
+    #[test]
+    fn frames_wrap_around_reversed() {
+        let mut m = marquee(&["-w", "4", "-s", "|", "-r"]);
+        m.push("abcdef".into(), None);
+        assert_eq!(
+            frames(&mut m, 8),
+            ["cdef", "bcde", "abcd", "|abc", "f|ab", "ef|a", "def|", "cdef"]
+        );
+    }
+
+    #[test]
+    fn frames_without_a_separator() {
+        let mut m = marquee(&["-w", "4", "-s", ""]);
+        m.push("abcdef".into(), None);
+        assert_eq!(
+            frames(&mut m, 7),
+            ["abcd", "bcde", "cdef", "defa", "efab", "fabc", "abcd"]
+        );
+    }
+
+    #[test]
+    fn frames_with_a_long_separator() {
+        // The separator is wider than the output, so some frames are only the separator
+        let mut m = marquee(&["-w", "2", "-s", " -- "]);
+        m.push("abc".into(), None);
+        assert_eq!(
+            frames(&mut m, 8),
+            ["ab", "bc", "c ", " -", "--", "- ", " a", "ab"]
+        );
+    }
+
+    #[test]
+    fn frames_that_fit_do_not_move() {
+        let mut m = marquee(&["-w", "4", "-s", "|"]);
+        m.push("abc".into(), None);
+        assert_eq!(frames(&mut m, 3), ["abc", "abc", "abc"]);
+        let mut m = marquee(&["-w", "4", "-s", "|"]);
+        m.push("abcd".into(), None);
+        assert_eq!(frames(&mut m, 2), ["abcd", "abcd"]);
+    }
+
+    #[test]
+    fn frames_of_empty_content() {
+        let mut m = marquee(&["-w", "4", "-s", "|"]);
+        m.push(String::new(), None);
+        assert_eq!(frames(&mut m, 3), ["", "", ""]);
+    }
+
+    #[test]
+    fn frames_of_wide_characters() {
+        // A wide character that is cut in half at the edge is replaced by a space
+        let mut m = marquee(&["-w", "4", "-s", "|"]);
+        m.set_widths(Widths::new(1, 2));
+        m.push("a漢字b".into(), None);
+        assert_eq!(
+            frames(&mut m, 8),
+            ["a漢 ", "漢字", " 字b", "字b|", " b|a", "b|a ", "|a漢", "a漢 "]
+        );
+    }
+
+    #[test]
+    fn frames_of_combining_marks() {
+        // The accent is drawn on top of the `e`, so they move together as one column
+        let mut m = marquee(&["-w", "3", "-s", "|"]);
+        m.set_widths(Widths::new(1, 2));
+        m.push("e\u{301}bcd".into(), None);
+        assert_eq!(
+            frames(&mut m, 6),
+            [
+                "e\u{301}bc",
+                "bcd",
+                "cd|",
+                "d|e\u{301}",
+                "|e\u{301}b",
+                "e\u{301}bc"
+            ]
+        );
+    }
+
+    #[test]
+    fn content_with_the_wide_tail() {
+        // It would otherwise be removed along with the ones that are put after wide characters
+        let mut m = marquee(&["-w", "4"]);
+        m.set_widths(Widths::new(1, 2));
+        m.push("a\u{fdd0}漢".into(), None);
+        assert_eq!(frames(&mut m, 1), ["a\u{fffd}漢"]);
+    }
+
+    #[test]
+    fn frames_of_multibyte_characters() {
+        // Without `--probe-widths` every character is one column
+        let mut m = marquee(&["-w", "3", "-s", "|"]);
+        m.push("é漢😀b".into(), None);
+        assert_eq!(
+            frames(&mut m, 6),
+            ["é漢😀", "漢😀b", "😀b|", "b|é", "|é漢", "é漢😀"]
+        );
+    }
+
+    #[test]
+    fn start_offset_with_nothing_to_move_through() {
+        // The content and the separator are both empty, so the cycle is 0 frames long
+        let mut m = marquee(&["-s", "", "--start-offset", "2"]);
+        m.push(String::new(), None);
+        assert_eq!(frames(&mut m, 2), ["", ""]);
+
+        // The control character is removed, which leaves nothing
+        let mut m = marquee(&["-s", "", "--start-offset", "2"]);
+        m.push(String::from("\x01"), None);
+        frames(&mut m, 2);
+
+        // A JSON message is still shown when its content is empty
+        let mut m = marquee(&["-j", "-w", "4", "-s", "", "--start-offset", "2"]);
+        m.push(String::from(r#"{"content": ""}"#), None);
+        m.seek(3);
+        assert_eq!(frames(&mut m, 2), ["    ", "    "]);
+    }
+
+    #[test]
+    fn age_of_the_message() {
+        let mut m = marquee(&["-w", "20", "--prefix", "[{age}] "]);
+        m.push("abc".into(), Some(Instant::now() - Duration::from_secs(90)));
+        assert_eq!(frames(&mut m, 1), ["[1m] abc"]);
+
+        // The age isn't known, so the frame doesn't depend on when it is worked out
+        let mut m = marquee(&["-w", "20", "--prefix", "[{age}] "]);
+        m.push("abc".into(), None);
+        assert_eq!(frames(&mut m, 1), ["[0s] abc"]);
+    }
+
+    #[test]
+    fn speed_ramp_starts_from_a_changed_delay() {
+        let mut m = marquee(&["-j", "-w", "4", "-d", "200"]);
+        m.push(r#"{"content": "abcdef", "speed_end": 100}"#.into(), None);
+        let frame = m.frame().unwrap().unwrap();
+        assert_eq!(frame.delay, Some(Duration::from_millis(200)));
+
+        m.set_delay(50);
+        let frame = m.frame().unwrap().unwrap();
+        assert_eq!(frame.delay, Some(Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn build_round_shows_each_message_weight_times() {
+        let round = build_round(&[5, 1, 1]);
+        assert_eq!(round.len(), 7);
+        for (i, weight) in [5, 1, 1].into_iter().enumerate() {
+            assert_eq!(round.iter().filter(|&&m| m == i).count(), weight);
+        }
+    }
+
+    #[test]
+    fn build_round_spreads_messages_out() {
+        assert_eq!(build_round(&[5, 1, 1]), [0, 0, 1, 0, 2, 0, 0]);
+        assert_eq!(build_round(&[2, 2]), [0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn build_round_treats_zero_weight_as_one() {
+        assert_eq!(build_round(&[0, 0]), [0, 1]);
+        assert_eq!(build_round(&[1]), [0]);
+    }
+}
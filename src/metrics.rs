@@ -0,0 +1,134 @@
+//! Serving the stats as Prometheus metrics (see `--metrics`)
+
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+use crate::stats::Stats;
+
+/// How long a client can take to send each part of its request (or to read the response) before
+/// it is given up on, since a client that never finishes would stop every other request from
+/// being answered
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Start the thread that serves the metrics on `addr`, using the latest copy of the stats in
+/// `stats`
+pub fn serve(addr: &str, stats: Arc<Mutex<Stats>>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    log::info!(
+        "Serving metrics on http://{}/metrics",
+        listener.local_addr()?
+    );
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| respond(stream, &stats));
+            if let Err(err) = result {
+                log::warn!("Failed to respond to a metrics request: {}", err);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Respond to a single request, only `GET /metrics` is supported
+fn respond(stream: TcpStream, stats: &Mutex<Stats>) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // The headers aren't needed, but they still have to be read before responding
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let stats = stats.lock().unwrap_or_else(PoisonError::into_inner);
+            ("200 OK", render(&stats))
+        }
+        _ => ("404 Not Found", String::from("Not Found\n")),
+    };
+
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Write the stats in the Prometheus text format
+fn render(stats: &Stats) -> String {
+    let metrics = [
+        (
+            "marquee_messages_received_total",
+            "Lines read from the input",
+            stats.messages as f64,
+        ),
+        (
+            "marquee_frames_shown_total",
+            "Frames that were shown",
+            stats.frames as f64,
+        ),
+        (
+            "marquee_frames_late_total",
+            "Frames that were shown at least one delay late",
+            stats.late as f64,
+        ),
+        (
+            "marquee_frames_dropped_total",
+            "Frames that were skipped to catch up",
+            stats.dropped as f64,
+        ),
+        (
+            "marquee_parse_errors_total",
+            "Lines that weren't valid JSON",
+            stats.parse_errors as f64,
+        ),
+        (
+            "marquee_source_restarts_total",
+            "Times that the source command was started again",
+            stats.restarts as f64,
+        ),
+        (
+            "marquee_frame_seconds_total",
+            "Time spent working out and printing frames",
+            stats.frame_time.as_secs_f64(),
+        ),
+    ];
+
+    let mut out = String::new();
+    for (name, help, value) in metrics {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_source_restarts() {
+        let stats = Stats {
+            restarts: 3,
+            ..Stats::default()
+        };
+        let out = render(&stats);
+        assert!(out.contains("# TYPE marquee_source_restarts_total counter\n"));
+        assert!(out.contains("\nmarquee_source_restarts_total 3\n"));
+    }
+}
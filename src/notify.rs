@@ -0,0 +1,53 @@
+//! Sending a desktop notification when the message changes (see `--notify-on-change`)
+
+use std::{
+    process::{Command, Stdio},
+    rc::Rc,
+    thread,
+};
+
+use crate::{marquee::content_of, Cli};
+
+/// Sends a freedesktop notification with the full message each time a new one is shown.
+///
+/// The notification is sent by `notify-send` (from libnotify), which talks to the notification
+/// server over D-Bus.
+#[derive(Debug, Default)]
+pub struct Notifier {
+    /// The message that was notified last, so that it is only sent when it changes
+    message: Option<Rc<str>>,
+}
+
+impl Notifier {
+    /// Send a notification for `message` if it isn't the same as the last one
+    pub fn notify(&mut self, options: &Cli, message: &Rc<str>) {
+        if self
+            .message
+            .as_ref()
+            .is_some_and(|m| Rc::ptr_eq(m, message))
+        {
+            return;
+        }
+        self.message = Some(Rc::clone(message));
+
+        // The content is shown rather than the JSON it came from
+        let body = content_of(options, message);
+        if body.trim().is_empty() {
+            return;
+        }
+
+        let child = Command::new("notify-send")
+            .args(["--app-name", "marquee", "--", "marquee", &body])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn();
+        match child {
+            // Waited for on another thread so that it doesn't hold up the frames, or stay around
+            // as a zombie
+            Ok(mut child) => {
+                thread::spawn(move || child.wait());
+            }
+            Err(err) => log::warn!("Failed to send a notification: {}", err),
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//! Printing the frames of the marquee
+
+use std::{
+    io::{self, BufWriter, IsTerminal, StderrLock, StdoutLock, Write},
+    rc::Rc,
+    str::FromStr,
+};
+
+#[cfg(feature = "hardware")]
+use crate::text::unescape;
+use crate::{marquee::Frame, text::visible_len, AlertEffect, Bell, Cli, When};
+
+/// Where the frames are shown (see `--output`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Print them to stdout
+    Stdout,
+    /// Show them on an HD44780 character LCD with a PCF8574 backpack, at `address` on the I2C bus
+    /// at `path`
+    #[cfg(feature = "hardware")]
+    Lcd {
+        path: std::path::PathBuf,
+        address: u16,
+    },
+    /// Write them to the serial device at `path`, at `baud` bits per second
+    #[cfg(feature = "hardware")]
+    Serial { path: std::path::PathBuf, baud: u32 },
+    /// Show them on a chain of `modules` MAX7219 LED matrices on the SPI device at `path`
+    #[cfg(feature = "hardware")]
+    Matrix {
+        path: std::path::PathBuf,
+        modules: usize,
+    },
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "stdout" {
+            return Ok(Target::Stdout);
+        }
+        let (kind, _rest) = s
+            .split_once(':')
+            .ok_or("expected `stdout` or the form `kind:path`")?;
+        match kind {
+            #[cfg(feature = "hardware")]
+            "lcd" => {
+                let (path, setting) = device(_rest);
+                let address = match setting {
+                    Some(a) => match a.strip_prefix("0x") {
+                        Some(hex) => u16::from_str_radix(hex, 16),
+                        None => a.parse(),
+                    }
+                    .map_err(|e| format!("invalid I2C address: {}", e))?,
+                    None => 0x27,
+                };
+                Ok(Target::Lcd {
+                    path: path.into(),
+                    address,
+                })
+            }
+            #[cfg(feature = "hardware")]
+            "max7219" => {
+                let (path, setting) = device(_rest);
+                let modules = match setting {
+                    Some(m) => m
+                        .parse()
+                        .map_err(|e| format!("invalid number of modules: {}", e))?,
+                    None => 4,
+                };
+                Ok(Target::Matrix {
+                    path: path.into(),
+                    modules,
+                })
+            }
+            #[cfg(feature = "hardware")]
+            "serial" => {
+                let (path, setting) = device(_rest);
+                let baud = match setting {
+                    Some(b) => b.parse().map_err(|e| format!("invalid baud rate: {}", e))?,
+                    None => 9600,
+                };
+                Ok(Target::Serial {
+                    path: path.into(),
+                    baud,
+                })
+            }
+            #[cfg(not(feature = "hardware"))]
+            "lcd" | "max7219" | "serial" => Err(String::from(
+                "marquee was built without the `hardware` feature",
+            )),
+            _ => Err(format!("unknown kind of output `{}`", kind)),
+        }
+    }
+}
+
+/// Split the path of a device from its setting, which goes after the last `@` (i.e.
+/// `/dev/i2c-1@0x27`)
+#[cfg(feature = "hardware")]
+fn device(s: &str) -> (&str, Option<&str>) {
+    match s.rsplit_once('@') {
+        Some((path, setting)) => (path, Some(setting)),
+        None => (s, None),
+    }
+}
+
+/// Somewhere that the frames can be shown
+pub trait Sink {
+    /// Show a single frame
+    fn print(&mut self, frame: &Frame) -> io::Result<()>;
+
+    /// Clean up once the marquee has stopped
+    fn finish(&mut self) -> io::Result<()>;
+
+    /// Remove the last frame instead of cleaning up (see `--on-finish`)
+    fn clear(&mut self) -> io::Result<()> {
+        self.finish()
+    }
+}
+
+/// Open the output that the frames should be shown on (see `--output`)
+pub fn open(options: &Cli) -> io::Result<Box<dyn Sink>> {
+    Ok(match &options.output {
+        Target::Stdout if options.status_line => {
+            Box::new(crate::status_line::StatusLine::new(options)?)
+        }
+        // Stdout is used for the input instead (`--passthrough`)
+        Target::Stdout if options.passthrough == Some(1) => Box::new(Printer::stderr(options)),
+        Target::Stdout => Box::new(Printer::new(options)),
+        #[cfg(feature = "hardware")]
+        Target::Lcd { path, address } => Box::new(crate::hardware::Lcd::open(path, *address)?),
+        #[cfg(feature = "hardware")]
+        Target::Matrix { path, modules } => {
+            Box::new(crate::hardware::Matrix::open(path, *modules)?)
+        }
+        #[cfg(feature = "hardware")]
+        Target::Serial { path, baud } => Box::new(crate::hardware::Serial::open(
+            path,
+            *baud,
+            unescape(&options.serial_prefix),
+            unescape(&options.serial_terminator),
+        )?),
+    })
+}
+
+/// Prints each frame to stdout (or anything else that can be written to), keeping track of what
+/// was printed last so that it can be overwritten when using `--same-line`
+#[derive(Debug)]
+pub struct Printer<W = BufWriter<StdoutLock<'static>>> {
+    /// Stdout is locked once for the whole run, and each frame is buffered and then written all
+    /// at once when it is flushed
+    out: W,
+    same_line: bool,
+    keep_duplicates: bool,
+    bell: Option<Bell>,
+    /// If the bell is rung for messages that match `--alert-on` (`--alert-effect bell`)
+    alert_bell: bool,
+    /// Wrap each frame in the escape codes for synchronized output (`--sync-output`)
+    synchronized: bool,
+    /// The message of the previous frame, so that the bell is only rung when it changes
+    message: Option<Rc<str>>,
+    /// The rows that were printed last time, so that the same frame isn't printed twice in a row
+    prev_frame: Vec<String>,
+    /// The length (in characters) of the line printed last time, this is used for `--same-line`
+    prev_len: usize,
+    /// The number of rows printed last time, this is used for `--rows` with `--same-line`
+    prev_rows: usize,
+}
+
+impl Printer {
+    pub fn new(options: &Cli) -> Self {
+        #[cfg(windows)]
+        enable_virtual_terminal(&io::stdout());
+
+        let mut printer = Self::with_writer(options, BufWriter::new(io::stdout().lock()));
+        printer.synchronized = synchronized(options, io::stdout().is_terminal());
+        printer
+    }
+}
+
+impl Printer<BufWriter<StderrLock<'static>>> {
+    /// Print the frames to stderr instead of stdout (see `--passthrough`)
+    pub fn stderr(options: &Cli) -> Self {
+        #[cfg(windows)]
+        enable_virtual_terminal(&io::stderr());
+
+        let mut printer = Self::with_writer(options, BufWriter::new(io::stderr().lock()));
+        printer.synchronized = synchronized(options, io::stderr().is_terminal());
+        printer
+    }
+}
+
+/// If each frame should be wrapped in the escape codes for synchronized output, when the output
+/// is (or isn't) a `terminal` (see `--sync-output`)
+fn synchronized(options: &Cli, terminal: bool) -> bool {
+    match options.sync_output {
+        When::Auto => options.same_line && terminal,
+        When::Always => options.same_line,
+        When::Never => false,
+    }
+}
+
+impl<W: Write> Printer<W> {
+    /// Print the frames to `out` instead of stdout
+    pub fn with_writer(options: &Cli, out: W) -> Self {
+        Self {
+            out,
+            same_line: options.same_line,
+            keep_duplicates: options.keep_duplicates,
+            bell: options.bell,
+            alert_bell: options.alert_effect == AlertEffect::Bell,
+            synchronized: false,
+            message: None,
+            prev_frame: Vec::new(),
+            prev_len: 0,
+            prev_rows: 0,
+        }
+    }
+
+    /// The writer that the frames are printed to
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    /// Print a single frame
+    pub fn print(&mut self, frame: &Frame) -> io::Result<()> {
+        // The first message isn't replacing anything, so it doesn't ring the bell (unless it is
+        // an alert)
+        match &self.message {
+            Some(m) if Rc::ptr_eq(m, &frame.message) => {}
+            prev => {
+                let bell = if frame.alert && self.alert_bell {
+                    Some(self.bell.unwrap_or(Bell::Bel))
+                } else {
+                    self.bell.filter(|_| prev.is_some())
+                };
+                if let Some(bell) = bell {
+                    match bell {
+                        Bell::Bel => write!(self.out, "\x07")?,
+                        Bell::Attention => write!(self.out, "\x1b]1337;RequestAttention=yes\x07")?,
+                    }
+                }
+                self.message = Some(Rc::clone(&frame.message));
+            }
+        }
+
+        let rows = &frame.rows;
+        // Printing content that isn't moving again would only cause flickering (or a duplicate
+        // line).  Moving content is always printed, since each line is a step of the animation.
+        if !self.keep_duplicates && !frame.rotating && *rows == self.prev_frame {
+            return Ok(());
+        }
+        self.prev_frame.clone_from(rows);
+
+        // The terminal holds off on showing anything until the whole frame has been written
+        if self.synchronized {
+            write!(self.out, "\x1b[?2026h")?;
+        }
+        if self.same_line && (rows.len() > 1 || self.prev_rows > 1) {
+            // Move back up to the first row that was printed last time and overwrite all of the
+            // rows, clearing anything that is left over
+            if self.prev_rows > 1 {
+                write!(self.out, "\x1b[{}A", self.prev_rows - 1)?;
+            }
+            write!(self.out, "\r{}\x1b[J", rows.join("\x1b[K\n"))?;
+            self.prev_rows = rows.len();
+        } else if self.same_line {
+            let out = &rows[0];
+            let len = visible_len(out);
+            write!(self.out, "\r{}", out)?;
+            if self.prev_len > len {
+                // Clear the rest of the line
+                write!(self.out, "{}", " ".repeat(self.prev_len - len))?;
+            }
+            self.prev_len = len;
+            self.prev_rows = 1;
+        } else {
+            for out in rows {
+                writeln!(self.out, "{}", out)?;
+            }
+        }
+        if self.synchronized {
+            write!(self.out, "\x1b[?2026l")?;
+        }
+        self.out.flush()
+    }
+
+    /// Remove the rows that were printed last time, leaving the cursor where the first one was
+    pub fn clear(&mut self) -> io::Result<()> {
+        let rows = if self.same_line {
+            self.prev_rows.saturating_sub(1)
+        } else {
+            self.prev_frame.len()
+        };
+        if rows > 0 {
+            write!(self.out, "\x1b[{}A", rows)?;
+        }
+        write!(self.out, "\r\x1b[J")?;
+        self.out.flush()
+    }
+
+    /// Move the cursor off of the marquee line so that anything printed after isn't put on it
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.same_line {
+            writeln!(self.out)?;
+        }
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Sink for Printer<W> {
+    fn print(&mut self, frame: &Frame) -> io::Result<()> {
+        Printer::print(self, frame)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Printer::finish(self)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        Printer::clear(self)
+    }
+}
+
+/// Turn on escape codes for the Windows console, which conhost doesn't handle unless asked to.
+/// This is done separately for stdout and stderr (`stream`), as they can be different consoles.
+///
+/// If the stream isn't a console (i.e. it's a pipe) then there is nothing to turn on, so any
+/// errors are ignored.
+#[cfg(windows)]
+pub fn enable_virtual_terminal(stream: &impl std::os::windows::io::AsRawHandle) {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+    };
+
+    // SAFETY: The handle belongs to `stream`, which outlives the call, and `mode` lives for the
+    // whole call
+    unsafe {
+        let handle = stream.as_raw_handle();
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) != 0 {
+            SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING);
+        }
+    }
+}
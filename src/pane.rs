@@ -0,0 +1,57 @@
+//! Reading the messages for the right pane (see `--right`)
+
+use std::{
+    io::{self, BufReader},
+    process::{Child, Stdio},
+    sync::mpsc::Sender,
+    thread,
+};
+
+use crate::{
+    error::Error,
+    input::{self, Event, ReadOptions},
+    source,
+};
+
+/// The command whose lines are shown in the right pane
+#[derive(Debug)]
+pub struct RightPane {
+    child: Child,
+}
+
+impl RightPane {
+    /// Start `cmd` with the shell, and start the thread that sends each line that it prints to
+    /// `events` (as `Event::RightLine`)
+    pub fn spawn(cmd: &str, events: Sender<Event>, read: ReadOptions) -> io::Result<Self> {
+        let mut child = source::shell(cmd).stdout(Stdio::piped()).spawn()?;
+        log::info!("Started the right pane command {:?} ({})", cmd, child.id());
+
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        thread::spawn(move || loop {
+            let line = match input::read_line(&mut stdout, read) {
+                Ok(Some((line, _))) => line,
+                Ok(None) => {
+                    // The left pane carries on, so this isn't treated like stdin being closed
+                    log::info!("The right pane command has stopped");
+                    return;
+                }
+                Err(err) => {
+                    let _ = events.send(Event::Error(Error::Right(err)));
+                    return;
+                }
+            };
+            if events.send(Event::RightLine(line)).is_err() {
+                return;
+            }
+        });
+
+        Ok(Self { child })
+    }
+}
+
+impl Drop for RightPane {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
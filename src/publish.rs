@@ -0,0 +1,157 @@
+//! Publishing the frames to an MQTT broker (see `--publish`)
+//!
+//! Only the small part of MQTT 3.1.1 that is needed is written here: connecting without a keep
+//! alive, and publishing with QoS 0.
+
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    rc::Rc,
+    str::FromStr,
+};
+
+use crate::{marquee::Frame, text::strip_escapes};
+
+/// Where to publish the frames, from `mqtt://host[:port]/topic`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Broker {
+    host: String,
+    port: u16,
+    topic: String,
+}
+
+impl FromStr for Broker {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix("mqtt://")
+            .ok_or_else(|| String::from("the url has to start with `mqtt://`"))?;
+        let (addr, topic) = rest
+            .split_once('/')
+            .filter(|(_, topic)| !topic.is_empty())
+            .ok_or_else(|| {
+                String::from("the url has to have a topic (i.e. `mqtt://host/topic`)")
+            })?;
+        let (host, port) = match addr.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse().map_err(|e| format!("invalid port: {}", e))?,
+            ),
+            None => (addr, 1883),
+        };
+        Ok(Self {
+            host: host.into(),
+            port,
+            topic: topic.into(),
+        })
+    }
+}
+
+/// Publishes the frames (or only the messages) that are shown to a topic
+#[derive(Debug)]
+pub struct Publisher {
+    stream: TcpStream,
+    topic: String,
+    /// Only publish the message when it changes, rather than every frame
+    messages: bool,
+    /// The message of the previous frame, so that it is only published when it changes
+    message: Option<Rc<str>>,
+}
+
+impl Publisher {
+    pub fn connect(broker: &Broker, messages: bool) -> io::Result<Self> {
+        let mut stream = TcpStream::connect((broker.host.as_str(), broker.port))?;
+        stream.set_nodelay(true)?;
+
+        let client_id = format!("marquee-{}", std::process::id());
+        let mut body = Vec::new();
+        write_str(&mut body, "MQTT");
+        // Protocol level 4 (3.1.1), a clean session, and no keep alive
+        body.extend_from_slice(&[4, 0x02, 0, 0]);
+        write_str(&mut body, &client_id);
+        write_packet(&mut stream, 0x10, &body)?;
+
+        let mut connack = [0; 4];
+        stream.read_exact(&mut connack)?;
+        if connack[0] != 0x20 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the broker didn't acknowledge the connection",
+            ));
+        }
+        if connack[3] != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("the broker refused the connection (code {})", connack[3]),
+            ));
+        }
+        log::info!(
+            "Publishing to {}:{} on {:?}",
+            broker.host,
+            broker.port,
+            broker.topic
+        );
+
+        Ok(Self {
+            stream,
+            topic: broker.topic.clone(),
+            messages,
+            message: None,
+        })
+    }
+
+    /// Publish a single frame, the rows are joined with `\n`
+    pub fn publish(&mut self, frame: &Frame) -> io::Result<()> {
+        let changed = !self
+            .message
+            .as_ref()
+            .is_some_and(|m| Rc::ptr_eq(m, &frame.message));
+        if changed {
+            self.message = Some(Rc::clone(&frame.message));
+        }
+
+        let payload = if self.messages {
+            if !changed {
+                return Ok(());
+            }
+            frame.message.to_string()
+        } else {
+            frame
+                .rows
+                .iter()
+                .map(|row| strip_escapes(row))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let mut body = Vec::new();
+        write_str(&mut body, &self.topic);
+        body.extend_from_slice(payload.as_bytes());
+        write_packet(&mut self.stream, 0x30, &body)
+    }
+}
+
+/// Write a string with its length in front of it
+fn write_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u16).to_be_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// Write a packet of `kind` (the first byte of the fixed header), the length of the rest of the
+/// packet is written 7 bits at a time
+fn write_packet(stream: &mut TcpStream, kind: u8, body: &[u8]) -> io::Result<()> {
+    let mut packet = vec![kind];
+    let mut len = body.len();
+    loop {
+        let byte = (len % 128) as u8;
+        len /= 128;
+        if len == 0 {
+            packet.push(byte);
+            break;
+        }
+        packet.push(byte | 0x80);
+    }
+    packet.extend_from_slice(body);
+    stream.write_all(&packet)
+}
@@ -0,0 +1,63 @@
+//! Python bindings for working out the frames of a marquee, this needs the `python` feature (see
+//! `marquee.pyi`).
+//!
+//! The module is built with `cargo rustc --release --lib --features python --crate-type cdylib`,
+//! and then `libmarquee.so` is renamed to `marquee.so` (or `marquee.pyd` on Windows).  Nothing is
+//! printed, and the caller has to wait `delay` seconds between each frame itself.
+
+use clap::Parser;
+use pyo3::{
+    exceptions::{PyIOError, PyValueError},
+    prelude::*,
+};
+
+use crate::{figlet::Font, Cli};
+
+/// A marquee that the frames can be taken from one at a time
+#[pyclass(name = "Marquee", unsendable)]
+pub struct Marquee {
+    marquee: crate::marquee::Marquee,
+    /// The number of seconds to wait between each frame
+    #[pyo3(get)]
+    delay: f64,
+}
+
+#[pymethods]
+impl Marquee {
+    /// Create a marquee from command line arguments (without the name of the program), which are
+    /// the same as the ones that the `marquee` command takes (i.e. `["--width", "20"]`)
+    #[new]
+    #[pyo3(signature = (args = Vec::new()))]
+    fn new(args: Vec<String>) -> PyResult<Self> {
+        let options = Cli::try_parse_from(std::iter::once(String::from("marquee")).chain(args))
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        let font =
+            Font::from_options(&options).map_err(|err| PyIOError::new_err(err.to_string()))?;
+        Ok(Self {
+            delay: options.frame_delay().as_secs_f64(),
+            marquee: crate::marquee::Marquee::new(options, font),
+        })
+    }
+
+    /// Show `text` on the marquee, this is the same as a line of input to the `marquee` command
+    /// (so it is JSON with `--json`, and an empty string clears it)
+    fn set_text(&mut self, text: String) {
+        self.marquee.push(text, None);
+    }
+
+    /// Work out the next frame, the rows are joined with `\n`.  Returns `None` if there is
+    /// nothing to show.
+    fn next_frame(&mut self) -> PyResult<Option<String>> {
+        let frame = self
+            .marquee
+            .frame()
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(frame.map(|frame| frame.rows.join("\n")))
+    }
+}
+
+/// The `marquee` module
+#[pymodule(name = "marquee")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Marquee>()
+}
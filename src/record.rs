@@ -0,0 +1,69 @@
+//! Recording the frames that are shown to a file (see `--record`)
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    rc::Rc,
+    time::Instant,
+};
+
+use crate::marquee::Frame;
+
+/// A single frame of a recording, each one is written as a line of JSON
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    /// Milliseconds since the recording started
+    pub time: u64,
+    /// The message that the frame was worked out from, this is only recorded when it changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// The rows of the frame
+    pub rows: Vec<String>,
+    /// If the content was moving, so that the frame is shown even if it is the same as the one
+    /// before it (see `Printer::print`)
+    #[serde(default)]
+    pub rotating: bool,
+}
+
+/// Writes each frame to the recording as it is shown
+#[derive(Debug)]
+pub struct Recorder {
+    out: BufWriter<File>,
+    start: Instant,
+    /// The message of the previous frame, so that it is only recorded when it changes
+    message: Option<Rc<str>>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            out: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+            message: None,
+        })
+    }
+
+    /// Record a single frame
+    pub fn record(&mut self, frame: &Frame) -> io::Result<()> {
+        let changed = !self
+            .message
+            .as_ref()
+            .is_some_and(|m| Rc::ptr_eq(m, &frame.message));
+        if changed {
+            self.message = Some(Rc::clone(&frame.message));
+        }
+
+        let entry = Entry {
+            time: self.start.elapsed().as_millis() as u64,
+            message: changed.then(|| frame.message.to_string()),
+            rows: frame.rows.clone(),
+            rotating: frame.rotating,
+        };
+        serde_json::to_writer(&mut self.out, &entry)?;
+        writeln!(self.out)?;
+        // The marquee is usually stopped by being killed, so each frame is written straight away
+        self.out.flush()
+    }
+}
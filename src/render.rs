@@ -0,0 +1,188 @@
+//! Working out a single frame of some text without keeping any state (see `render_frame`)
+
+use clap::{error::ErrorKind, Parser};
+
+use crate::{error::Error, figlet::Font, marquee::Marquee, Cli, ScrollUnit};
+
+/// The options to work out frames with, which are the same as the arguments that the `marquee`
+/// command takes
+#[derive(Debug, Clone)]
+pub struct Config {
+    options: Cli,
+    /// The font is loaded once here, so that working out a frame never reads anything
+    font: Option<Font>,
+}
+
+impl Config {
+    /// Parse `args` (without the name of the program, i.e. `["--width", "20"]`), and load the font
+    /// if there is one (`--figlet`)
+    pub fn from_args<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let args = std::iter::once(String::from("marquee")).chain(args.into_iter().map(Into::into));
+        let options = Cli::try_parse_from(args)?;
+        let font = Font::from_options(&options).map_err(|err| {
+            clap::Error::raw(ErrorKind::Io, format!("failed to load the font: {}", err))
+        })?;
+        Ok(Self { options, font })
+    }
+}
+
+/// Work out what the marquee shows `frame_index` frames after `text` comes in (with the rows
+/// joined by `\n`), without printing anything or waiting between frames.
+///
+/// This is the frame that the marquee shows once it has shown `frame_index` frames of `text`
+/// (which the tests check), so `text` is JSON with `--json`.  It isn't the code that the `marquee`
+/// command runs though: the marquee is made again for every call, and with `--by words` every
+/// frame before `frame_index` is worked out as well, so it is slow for working out every frame in
+/// turn.  An empty string is returned if there is nothing to show (i.e. `text` is empty).
+///
+/// Note: `--separator-order random` and `--shuffle` still pick at random, and anything that
+/// depends on time (i.e. `{age}`) is as if no time has passed
+pub fn render_frame(config: &Config, text: &str, frame_index: usize) -> Result<String, Error> {
+    let mut marquee = Marquee::new(config.options.clone(), config.font.clone());
+    marquee.push(text.to_string(), None);
+    if config.options.by == ScrollUnit::Chars {
+        marquee.seek(frame_index);
+    } else {
+        // The content can't be moved straight to a word, so the frames before it are worked out
+        for _ in 0..frame_index {
+            marquee.frame()?;
+        }
+    }
+    Ok(marquee
+        .frame()?
+        .map(|frame| frame.rows.join("\n"))
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The number of random cases that each property is checked with
+    const CASES: usize = 200;
+
+    /// Random content made of words of ASCII and multibyte characters
+    fn content(rng: &mut fastrand::Rng) -> String {
+        const CHARS: &[char] = &['a', 'b', 'c', 'x', 'y', 'z', 'é', 'ß', '漢', '😀'];
+        let words: Vec<String> = (0..rng.usize(1..6))
+            .map(|_| {
+                (0..rng.usize(1..8))
+                    .map(|_| CHARS[rng.usize(..CHARS.len())])
+                    .collect()
+            })
+            .collect();
+        words.join(" ")
+    }
+
+    /// Random arguments that change how the content moves
+    fn args(rng: &mut fastrand::Rng) -> Vec<String> {
+        let mut args = vec![
+            String::from("--width"),
+            rng.usize(1..16).to_string(),
+            format!("--separator={}", [" ", " | ", "", "--"][rng.usize(..4)]),
+        ];
+        if rng.bool() {
+            args.push(String::from("--reverse"));
+        }
+        if rng.bool() {
+            args.push(String::from("--by"));
+            args.push(String::from("words"));
+        }
+        if rng.u8(..4) == 0 {
+            args.push(String::from("--smooth"));
+        }
+        if rng.u8(..4) == 0 {
+            args.push(String::from("--gap-frames"));
+            args.push(rng.usize(1..4).to_string());
+        }
+        args
+    }
+
+    /// The frames that a marquee shows when it is stepped through from the start, which is what
+    /// the `marquee` command does
+    fn stepped(config: &Config, text: &str, count: usize) -> Vec<String> {
+        let mut marquee = Marquee::new(config.options.clone(), config.font.clone());
+        marquee.push(text.to_string(), None);
+        (0..count)
+            .map(|_| {
+                marquee
+                    .frame()
+                    .unwrap()
+                    .map(|frame| frame.rows.join("\n"))
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_as_stepping_through() {
+        let mut rng = fastrand::Rng::with_seed(0x6d61_7271);
+        for _ in 0..CASES {
+            let args = args(&mut rng);
+            let text = content(&mut rng);
+            let config = Config::from_args(args.clone()).unwrap();
+            let count = rng.usize(1..80);
+            let expected = stepped(&config, &text, count);
+            for (i, expected) in expected.iter().enumerate() {
+                assert_eq!(
+                    &render_frame(&config, &text, i).unwrap(),
+                    expected,
+                    "frame {} of {:?} with {:?}",
+                    i,
+                    text,
+                    args
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn repeats_every_cycle() {
+        let mut rng = fastrand::Rng::with_seed(0x6379_636c);
+        for _ in 0..CASES {
+            let width = rng.usize(1..16);
+            let separator = [" ", " | ", "--"][rng.usize(..3)];
+            let text = content(&mut rng);
+            let args = [
+                format!("--width={}", width),
+                format!("--separator={}", separator),
+            ];
+            let config = Config::from_args(args.clone()).unwrap();
+            // Each frame moves the content on by one character, until it is back at the start
+            let cycle = text.chars().count() + separator.chars().count();
+            let i = rng.usize(..1000);
+            assert_eq!(
+                render_frame(&config, &text, i).unwrap(),
+                render_frame(&config, &text, i + cycle).unwrap(),
+                "frame {} of {:?} with {:?}",
+                i,
+                text,
+                args
+            );
+        }
+    }
+
+    #[test]
+    fn fills_the_width() {
+        let mut rng = fastrand::Rng::with_seed(0x7769_6474);
+        for _ in 0..CASES {
+            let width = rng.usize(1..16);
+            let text = content(&mut rng);
+            let config = Config::from_args(["--width", &width.to_string()]).unwrap();
+            let frame = render_frame(&config, &text, rng.usize(..1000)).unwrap();
+            // Content that fits is shown as it is, otherwise each frame is exactly as wide
+            let expected = text.chars().count().min(width);
+            assert_eq!(frame.chars().count(), expected, "{:?} in {:?}", text, frame);
+        }
+    }
+
+    #[test]
+    fn nothing_to_show() {
+        let config = Config::from_args(["--separator="]).unwrap();
+        assert_eq!(render_frame(&config, "", 5).unwrap(), "");
+    }
+}
@@ -0,0 +1,66 @@
+//! Playing back a recording that was made with `--record` (see `marquee replay`)
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::{Error, Exit},
+    marquee::Frame,
+    output::Sink,
+    record::Entry,
+};
+
+/// Parse the speed multiplier for `--speed`, which has to be more than zero
+pub fn parse_speed(s: &str) -> Result<f64, String> {
+    let speed: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if speed.is_finite() && speed > 0.0 {
+        Ok(speed)
+    } else {
+        Err(String::from("the speed must be more than zero"))
+    }
+}
+
+/// Show each frame of the recording at `path` with `printer`, at the same time after the start
+/// that it was recorded at (divided by `speed`)
+pub fn replay(path: &Path, speed: f64, printer: &mut dyn Sink) -> Result<Exit, Error> {
+    let file = File::open(path).map_err(Error::Replay)?;
+    let start = Instant::now();
+    // The message is only recorded when it changes, so this is the one from the last entry that
+    // had it
+    let mut message: Rc<str> = Rc::from("");
+
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(Error::Replay)?;
+        if line.is_empty() {
+            continue;
+        }
+        let entry: Entry = serde_json::from_str(&line)
+            .map_err(io::Error::from)
+            .map_err(Error::Replay)?;
+        if let Some(m) = entry.message {
+            message = Rc::from(m);
+        }
+
+        let at = start + Duration::from_millis(entry.time).div_f64(speed);
+        thread::sleep(at.saturating_duration_since(Instant::now()));
+
+        let frame = Frame {
+            rows: entry.rows,
+            message: Rc::clone(&message),
+            rotating: entry.rotating,
+            finished: false,
+            cycle_end: false,
+            delay: None,
+            alert: false,
+        };
+        printer.print(&frame).map_err(Error::Output)?;
+    }
+
+    Ok(Exit::Finished)
+}
@@ -0,0 +1,261 @@
+//! Reading the messages from a plugin instead of stdin (see `--source-cmd`)
+//!
+//! The plugin is a command that prints a line of JSON (see `--json`) for each message, and which
+//! is sent a line of JSON on its stdin when something happens to the marquee (see `Control`).
+
+use std::{
+    io::{self, BufReader, Write},
+    process::{Child, Command, Stdio},
+    rc::Rc,
+    sync::mpsc::{self, Sender, SyncSender, TrySendError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{
+    input::{self, Event, ReadOptions},
+    marquee::Frame,
+};
+
+/// Something that happened to the marquee, which is sent to the plugin
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Control<'a> {
+    /// A message has started being shown
+    Shown { message: &'a str },
+    /// A cycle of the message has finished being shown
+    CycleEnd,
+    /// The message was skipped (i.e. with `SIGUSR1`)
+    Skip,
+}
+
+/// How long to wait before starting the plugin again the first time that it exits, this is
+/// doubled each time that it exits again soon after (`--restart-source`)
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+/// The longest to wait before starting the plugin again, once it has run for this long the wait
+/// goes back to `MIN_BACKOFF`
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The most events that can be waiting to be written to the plugin, any more are dropped until it
+/// has read some of them
+const QUEUE_LEN: usize = 64;
+
+/// A plugin that is running
+#[derive(Debug)]
+pub struct Source {
+    cmd: String,
+    child: Child,
+    /// The lines to write to the plugin's stdin, which is done on another thread (see `send`)
+    stdin: Option<SyncSender<String>>,
+    /// If events are being dropped because the plugin isn't reading them, so that it is only
+    /// warned about once each time that it falls behind
+    dropping: bool,
+    /// The message of the previous frame, so that `Shown` is only sent when it changes
+    message: Option<Rc<str>>,
+    /// Where the lines are sent and how they are read, which is kept to start the plugin again
+    events: Sender<Event>,
+    read: ReadOptions,
+    /// If the plugin is started again when it exits, when it was started, and how long to wait
+    /// before starting it again the next time (`--restart-source`)
+    restart: bool,
+    started: Instant,
+    backoff: Duration,
+}
+
+impl Source {
+    /// Start `cmd` with the shell, and start the thread that sends each line that it prints to
+    /// `events`, which is the same as reading stdin
+    pub fn spawn(
+        cmd: &str,
+        events: Sender<Event>,
+        read: ReadOptions,
+        restart: bool,
+    ) -> io::Result<Self> {
+        let (child, stdin) = start(cmd, &events, read, restart)?;
+        Ok(Self {
+            cmd: cmd.to_string(),
+            child,
+            stdin,
+            dropping: false,
+            message: None,
+            events,
+            read,
+            restart,
+            started: Instant::now(),
+            backoff: MIN_BACKOFF,
+        })
+    }
+
+    /// Handle the plugin closing its output (see `Event::SourceExited`), which starts it again
+    /// once it has waited for the backoff
+    pub fn exited(&mut self) {
+        // It has usually exited already, but it can't be left running if it only closed stdout
+        let _ = self.child.kill();
+        let status = match self.child.wait() {
+            Ok(status) => status.to_string(),
+            Err(err) => err.to_string(),
+        };
+        if self.started.elapsed() >= MAX_BACKOFF {
+            self.backoff = MIN_BACKOFF;
+        }
+        log::warn!(
+            "The source command stopped ({}), starting it again in {:?}",
+            status,
+            self.backoff
+        );
+
+        let (events, backoff) = (self.events.clone(), self.backoff);
+        thread::spawn(move || {
+            thread::sleep(backoff);
+            let _ = events.send(Event::RestartSource);
+        });
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+    }
+
+    /// Start the plugin again after it has exited
+    pub fn restart(&mut self) -> io::Result<()> {
+        let (child, stdin) = start(&self.cmd, &self.events, self.read, self.restart)?;
+        self.child = child;
+        self.stdin = stdin;
+        self.dropping = false;
+        // The new plugin hasn't been told what is being shown
+        self.message = None;
+        self.started = Instant::now();
+        Ok(())
+    }
+
+    /// Tell the plugin that `event` happened.
+    ///
+    /// The events are written on another thread, so the marquee doesn't stop when the plugin
+    /// doesn't read them.  Once `QUEUE_LEN` of them are waiting, the rest are dropped until the
+    /// plugin catches up, and once they can't be written at all no more are sent.
+    pub fn send(&mut self, event: Control) {
+        let Some(stdin) = &self.stdin else {
+            return;
+        };
+        let mut line = serde_json::to_string(&event).expect("events can always be serialized");
+        line.push('\n');
+        match stdin.try_send(line) {
+            Ok(()) => self.dropping = false,
+            Err(TrySendError::Full(_)) => {
+                if !self.dropping {
+                    log::warn!("The source command isn't reading its events, so they are dropped");
+                }
+                self.dropping = true;
+            }
+            Err(TrySendError::Disconnected(_)) => self.stdin = None,
+        }
+    }
+
+    /// Send the events for a frame that was shown
+    pub fn frame(&mut self, frame: &Frame) {
+        if !self
+            .message
+            .as_ref()
+            .is_some_and(|m| Rc::ptr_eq(m, &frame.message))
+        {
+            self.message = Some(Rc::clone(&frame.message));
+            self.send(Control::Shown {
+                message: &frame.message,
+            });
+        }
+        if frame.cycle_end {
+            self.send(Control::CycleEnd);
+        }
+    }
+}
+
+impl Drop for Source {
+    fn drop(&mut self) {
+        // Closing stdin (once the events that are waiting have been written) lets the plugin know
+        // that the marquee is done, but it is killed anyway in case it doesn't read it
+        self.stdin = None;
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Start `cmd` with the shell, and the threads that read its output and write to its input.  Once
+/// the output is closed, `Event::SourceExited` is sent if it should be started again, otherwise
+/// `Event::Eof`.
+fn start(
+    cmd: &str,
+    events: &Sender<Event>,
+    read: ReadOptions,
+    restart: bool,
+) -> io::Result<(Child, Option<SyncSender<String>>)> {
+    let mut child = shell(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    log::info!("Started the source command {:?} ({})", cmd, child.id());
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let events = events.clone();
+    thread::spawn(move || {
+        if input::read_lines(&events, BufReader::new(stdout), read) {
+            let _ = events.send(match restart {
+                true => Event::SourceExited,
+                false => Event::Eof,
+            });
+        }
+    });
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let (lines, rx) = mpsc::sync_channel::<String>(QUEUE_LEN);
+    thread::spawn(move || {
+        // This stops (closing stdin) once the plugin stops reading, or the `Source` is dropped
+        for line in rx {
+            if let Err(err) = stdin.write_all(line.as_bytes()) {
+                log::debug!("Stopped sending events to the source command: {}", err);
+                return;
+            }
+        }
+    });
+    Ok((child, Some(lines)))
+}
+
+/// A command that runs `cmd` with the shell
+pub fn shell(cmd: &str) -> Command {
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    };
+    #[cfg(not(windows))]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(cmd);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::Cli;
+
+    #[test]
+    #[cfg(unix)]
+    fn plugin_that_never_reads_its_events() {
+        let options = Cli::try_parse_from(["marquee"]).unwrap();
+        let (events, _rx) = mpsc::channel();
+        let read = ReadOptions::from_options(&options);
+        let mut source = Source::spawn("exec sleep 10", events, read, false).unwrap();
+
+        // Far more than fits in the pipe, which would block forever if they were written here
+        let start = Instant::now();
+        for _ in 0..100_000 {
+            source.send(Control::CycleEnd);
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(source.dropping);
+    }
+}
@@ -0,0 +1,38 @@
+//! Keeping track of how well the marquee is running (see `--stats`)
+
+use std::{fmt, time::Duration};
+
+/// Counts of what has happened since the marquee started
+#[derive(Debug, Default, Clone)]
+pub struct Stats {
+    /// The number of lines read from the input
+    pub messages: usize,
+    /// The number of frames that were shown
+    pub frames: usize,
+    /// The number of frames that were shown at least one `--delay` late
+    pub late: usize,
+    /// The number of frames that were skipped to catch up (see `--catch-up`)
+    pub dropped: usize,
+    /// The number of lines that weren't valid JSON (see `--json`)
+    pub parse_errors: usize,
+    /// The number of times that the source command was started again (see `--restart-source`)
+    pub restarts: usize,
+    /// The total time spent working out and printing the frames that were shown
+    pub frame_time: Duration,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let average = match u32::try_from(self.frames) {
+            Ok(0) | Err(_) => Duration::ZERO,
+            Ok(frames) => self.frame_time / frames,
+        };
+        writeln!(f, "messages received: {}", self.messages)?;
+        writeln!(f, "frames shown:      {}", self.frames)?;
+        writeln!(f, "frames late:       {}", self.late)?;
+        writeln!(f, "frames dropped:    {}", self.dropped)?;
+        writeln!(f, "parse errors:      {}", self.parse_errors)?;
+        writeln!(f, "source restarts:   {}", self.restarts)?;
+        writeln!(f, "average frame:     {:?}", average)
+    }
+}
@@ -0,0 +1,128 @@
+//! Keeping the marquee on the bottom row of the terminal (see `--status-line`)
+//!
+//! The rest of the terminal is made into a scroll region (with `DECSTBM`), so anything else that
+//! is written to it (i.e. by `tee /dev/tty`) scrolls above the marquee rather than over it.
+
+use std::{
+    io::{self, BufWriter, StdoutLock, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{marquee::Frame, output::Sink, Cli, When};
+
+#[derive(Debug)]
+pub struct StatusLine {
+    out: BufWriter<StdoutLock<'static>>,
+    /// The number of rows in the terminal, this is 0 until the scroll region has been set up
+    height: usize,
+    /// Set when the terminal is resized, so that the scroll region is set up again
+    resized: Arc<AtomicBool>,
+    /// The row that was printed last time, so that the same frame isn't printed twice in a row
+    prev: String,
+    /// Wrap each frame in the escape codes for synchronized output (`--sync-output`)
+    synchronized: bool,
+}
+
+impl StatusLine {
+    pub fn new(options: &Cli) -> io::Result<Self> {
+        #[cfg(windows)]
+        crate::output::enable_virtual_terminal(&io::stdout());
+
+        let resized = Arc::new(AtomicBool::new(false));
+        #[cfg(unix)]
+        signal_hook::flag::register(signal_hook::consts::SIGWINCH, Arc::clone(&resized))?;
+        Ok(Self {
+            out: BufWriter::new(io::stdout().lock()),
+            height: 0,
+            resized,
+            prev: String::new(),
+            // This only makes sense in a terminal anyway, so `auto` is the same as `always`
+            synchronized: options.sync_output != When::Never,
+        })
+    }
+
+    /// Make every row other than the last one into the scroll region, and leave the cursor where
+    /// it was (or one row up if it was on the last row)
+    fn set_up(&mut self) -> io::Result<()> {
+        let height = terminal_height()?;
+        if self.height == 0 {
+            // Make sure that there is a row free for the marquee, the cursor is moved back up to
+            // where it was below
+            writeln!(self.out)?;
+        }
+        write!(
+            self.out,
+            "\x1b7\x1b[1;{}r\x1b8",
+            height.saturating_sub(1).max(1)
+        )?;
+        if self.height == 0 {
+            write!(self.out, "\x1b[1A")?;
+        }
+        self.height = height;
+        Ok(())
+    }
+}
+
+impl Sink for StatusLine {
+    fn print(&mut self, frame: &Frame) -> io::Result<()> {
+        if self.height == 0 || self.resized.swap(false, Ordering::Relaxed) {
+            self.set_up()?;
+        } else if !frame.rotating && frame.rows[0] == self.prev {
+            return Ok(());
+        }
+        self.prev.clone_from(&frame.rows[0]);
+
+        if self.synchronized {
+            write!(self.out, "\x1b[?2026h")?;
+        }
+        // Only the first row is shown, since there is only one row to show it on
+        write!(
+            self.out,
+            "\x1b7\x1b[{};1H\x1b[2K{}\x1b8",
+            self.height, frame.rows[0]
+        )?;
+        if self.synchronized {
+            write!(self.out, "\x1b[?2026l")?;
+        }
+        self.out.flush()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if self.height > 0 {
+            // Give the whole terminal back, and remove the marquee
+            write!(self.out, "\x1b7\x1b[r\x1b[{};1H\x1b[2K\x1b8", self.height)?;
+        }
+        self.out.flush()
+    }
+}
+
+/// The number of rows in the terminal, which is read from `stty` (or `LINES` when it can't be run)
+fn terminal_height() -> io::Result<usize> {
+    #[cfg(unix)]
+    {
+        let output = std::process::Command::new("stty")
+            .arg("size")
+            .stdin(std::fs::File::open("/dev/tty")?)
+            .output();
+        let rows = output.ok().and_then(|output| {
+            let size = String::from_utf8(output.stdout).ok()?;
+            size.split_whitespace().next()?.parse().ok()
+        });
+        if let Some(rows) = rows {
+            return Ok(rows);
+        }
+    }
+
+    std::env::var("LINES")
+        .ok()
+        .and_then(|lines| lines.trim().parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "couldn't find the size of the terminal",
+            )
+        })
+}
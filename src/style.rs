@@ -0,0 +1,219 @@
+//! Styling the content with escape codes (see `--bold` and friends)
+
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, sync::LazyLock};
+
+use crate::{Cli, Color};
+
+/// The log levels that `log_level` looks for, this is only compiled once
+static LOG_LEVEL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(TRACE|DEBUG|INFO|WARN|WARNING|ERROR|FATAL|CRITICAL)\b").expect("valid regex")
+});
+
+/// The text attributes that the content is shown with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub blink: bool,
+    /// Swap the foreground and background colors (see `--alert-effect invert`)
+    pub reverse: bool,
+    pub color: Option<Color>,
+}
+
+/// The attributes that a line of JSON input sets, anything that isn't set is taken from the
+/// command line
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+pub struct JsonStyle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dim: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underline: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blink: Option<bool>,
+}
+
+impl Style {
+    pub fn from_options(options: &Cli) -> Self {
+        Self {
+            bold: options.bold,
+            dim: options.dim,
+            italic: options.italic,
+            underline: options.underline,
+            blink: options.blink,
+            reverse: false,
+            color: None,
+        }
+    }
+
+    /// The style of the separator between each repeat of the content (see `--separator-color`)
+    pub fn separator(options: &Cli) -> Self {
+        Self {
+            dim: options.separator_dim,
+            color: options.separator_color,
+            ..Self::default()
+        }
+    }
+
+    /// This style with anything that `json` sets replaced
+    pub fn with(self, json: &JsonStyle) -> Self {
+        Self {
+            bold: json.bold.unwrap_or(self.bold),
+            dim: json.dim.unwrap_or(self.dim),
+            italic: json.italic.unwrap_or(self.italic),
+            underline: json.underline.unwrap_or(self.underline),
+            blink: json.blink.unwrap_or(self.blink),
+            reverse: self.reverse,
+            color: self.color,
+        }
+    }
+
+    /// The parameters of the escape code for this style (i.e. `1;4` for bold and underlined)
+    fn codes(&self) -> Vec<String> {
+        let mut codes = Vec::new();
+        for (set, code) in [
+            (self.bold, "1"),
+            (self.dim, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+            (self.blink, "5"),
+            (self.reverse, "7"),
+        ] {
+            if set {
+                codes.push(code.to_string());
+            }
+        }
+        if let Some(color) = self.color {
+            codes.push((30 + color as u8).to_string());
+        }
+        codes
+    }
+
+    /// The escape code that resets anything that was set before, and then sets this style
+    fn reset_to(&self) -> String {
+        let mut codes = self.codes();
+        codes.insert(0, String::from("0"));
+        format!("\x1b[{}m", codes.join(";"))
+    }
+
+    /// If this style doesn't change how anything looks
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Wrap `text` in the escape codes for this style, resetting them afterwards so that they
+    /// don't carry on into the suffix (or anything printed after the marquee)
+    pub fn apply(&self, text: &str) -> String {
+        if self.is_plain() || text.is_empty() {
+            text.to_string()
+        } else {
+            format!("\x1b[{}m{}\x1b[0m", self.codes().join(";"), text)
+        }
+    }
+
+    /// Show each character of `text` in the style that `style_at` gives for it (by its index), or
+    /// in `rest` if it doesn't give one, which is the style that the whole of `text` is shown in
+    pub fn apply_each(
+        text: &str,
+        rest: &Style,
+        style_at: impl Fn(usize) -> Option<Style>,
+    ) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut current = None;
+        for (i, c) in text.chars().enumerate() {
+            let style = style_at(i);
+            if style != current {
+                // Each one resets anything that the one before it set, before setting its own
+                out += &style.unwrap_or(*rest).reset_to();
+                current = style;
+            }
+            out.push(c);
+        }
+        if current.is_some() {
+            out += &rest.reset_to();
+        }
+        out
+    }
+}
+
+/// Text that is shown in a different color wherever it is in the content (see `--highlight`)
+#[derive(Debug, Clone)]
+pub struct Highlight {
+    regex: Regex,
+    color: Color,
+}
+
+impl FromStr for Highlight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // The color can't contain `=`, but the pattern can
+        let (pattern, color) = s
+            .rsplit_once('=')
+            .ok_or("expected the form `pattern=color`")?;
+        Ok(Self {
+            regex: Regex::new(pattern).map_err(|e| e.to_string())?,
+            color: Color::from_str(color, true)?,
+        })
+    }
+}
+
+/// The color for the first log level in `text` (i.e. `WARN` is yellow), which is the same as the
+/// colors that `env_logger` uses (see `--loglevel-colors`)
+pub fn log_level(text: &str) -> Option<Color> {
+    let color = match LOG_LEVEL.find(text)?.as_str() {
+        "TRACE" => Color::Cyan,
+        "DEBUG" => Color::Blue,
+        "INFO" => Color::Green,
+        "WARN" | "WARNING" => Color::Yellow,
+        _ => Color::Red,
+    };
+    Some(color)
+}
+
+/// The color of each character of `text` from the highlights that match it, the later ones take
+/// precedence when they overlap
+pub fn highlight(highlights: &[Highlight], text: &str) -> Vec<Option<Color>> {
+    let mut colors = vec![None; text.chars().count()];
+    if highlights.is_empty() {
+        return colors;
+    }
+    // The matches are found by byte, so this finds the character that each byte is part of
+    let mut chars = vec![0; text.len() + 1];
+    for (i, (b, _)) in text.char_indices().enumerate() {
+        chars[b] = i;
+    }
+    chars[text.len()] = colors.len();
+    for highlight in highlights {
+        for m in highlight.regex.find_iter(text) {
+            colors[chars[m.start()]..chars[m.end()]].fill(Some(highlight.color));
+        }
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_level_uses_the_first_level() {
+        assert_eq!(log_level("INFO then ERROR"), Some(Color::Green));
+        assert_eq!(log_level("[WARNING] disk"), Some(Color::Yellow));
+        assert_eq!(log_level("FATAL: out of memory"), Some(Color::Red));
+    }
+
+    #[test]
+    fn log_level_only_matches_whole_words() {
+        assert_eq!(log_level("INFORMATION"), None);
+        assert_eq!(log_level("no level here"), None);
+    }
+}
@@ -0,0 +1,82 @@
+//! Lining up the frames of several marquees with each other (see `--sync-group`)
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// A clock that is shared by every marquee in the same group, frame `n` is shown `n * delay`
+/// after the group was started
+#[derive(Debug, Clone)]
+pub struct SyncClock {
+    start: SystemTime,
+    delay: Duration,
+}
+
+impl SyncClock {
+    /// Join the group called `name`, starting it if this is the first marquee in it.
+    ///
+    /// The start of the group is kept in a file in the temporary directory, so it stays the same
+    /// for as long as that file is there.
+    pub fn join(name: &str, delay: Duration) -> io::Result<Self> {
+        let path = Self::path(name);
+        let start = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                let now = SystemTime::now();
+                let millis = now
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis();
+                write!(file, "{}", millis)?;
+                now
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => Self::read(&path)?,
+            Err(err) => return Err(err),
+        };
+        log::info!("Joined sync group {:?} ({})", name, path.display());
+        Ok(Self { start, delay })
+    }
+
+    /// The file that holds the start of the group called `name`
+    fn path(name: &str) -> PathBuf {
+        let name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        std::env::temp_dir().join(format!("marquee-sync-{}", name))
+    }
+
+    /// Read the start of the group, waiting a little if another marquee is still writing it
+    fn read(path: &PathBuf) -> io::Result<SystemTime> {
+        for _ in 0..10 {
+            if let Ok(millis) = fs::read_to_string(path)?.trim().parse() {
+                return Ok(UNIX_EPOCH + Duration::from_millis(millis));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} doesn't hold a valid start time", path.display()),
+        ))
+    }
+
+    /// The frame that should be shown now, and how long it is until the next one
+    pub fn tick(&self) -> (usize, Duration) {
+        if self.delay.is_zero() {
+            return (0, Duration::ZERO);
+        }
+        let elapsed = self.start.elapsed().unwrap_or_default();
+        let tick = elapsed.as_nanos() / self.delay.as_nanos();
+        let next = Duration::from_nanos(((tick + 1) * self.delay.as_nanos()) as u64);
+        (tick as usize, next.saturating_sub(elapsed))
+    }
+}
@@ -0,0 +1,464 @@
+//! Transformations that are applied to the content before it is shown
+
+use regex::{Regex, RegexBuilder};
+use std::{str::FromStr, time::Duration};
+use unicode_bidi::{BidiInfo, Level};
+
+use crate::{width::WIDE_TAIL, Align, ControlChars, Direction, Spinner};
+
+/// A sed-style substitution that is applied to the content (see `--replace`)
+#[derive(Debug, Clone)]
+pub struct Replace {
+    regex: Regex,
+    replacement: String,
+    /// If every match should be replaced, rather than just the first one (the `g` flag)
+    global: bool,
+}
+
+impl Replace {
+    pub fn apply(&self, text: &str) -> String {
+        if self.global {
+            self.regex.replace_all(text, &self.replacement).into_owned()
+        } else {
+            self.regex.replace(text, &self.replacement).into_owned()
+        }
+    }
+}
+
+impl FromStr for Replace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if chars.next() != Some('s') {
+            return Err(String::from(
+                "expected the form `s/pattern/replacement/flags`",
+            ));
+        }
+        let delim = chars.next().ok_or("missing delimiter after `s`")?;
+
+        // Split the rest into the pattern, replacement, and flags on the (unescaped) delimiter
+        let mut parts = vec![String::new()];
+        while let Some(c) = chars.next() {
+            let part = parts.last_mut().expect("there is always a part");
+            match c {
+                '\\' => match chars.next() {
+                    Some(c) if c == delim => part.push(c),
+                    Some(c) => {
+                        part.push('\\');
+                        part.push(c);
+                    }
+                    None => part.push('\\'),
+                },
+                c if c == delim => parts.push(String::new()),
+                c => part.push(c),
+            }
+        }
+        let [pattern, replacement, flags] = <[String; 3]>::try_from(parts)
+            .map_err(|_| String::from("expected the form `s/pattern/replacement/flags`"))?;
+
+        let mut global = false;
+        let mut insensitive = false;
+        for flag in flags.chars() {
+            match flag {
+                'g' => global = true,
+                'i' => insensitive = true,
+                _ => return Err(format!("unknown flag `{}`", flag)),
+            }
+        }
+
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(insensitive)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        // Convert sed-style group references (`\1`) into the regex crate's style (`${1}`)
+        let group = Regex::new(r"\\(\d)").expect("valid regex");
+        let replacement = group.replace_all(&replacement, "$${$1}").into_owned();
+
+        Ok(Self {
+            regex,
+            replacement,
+            global,
+        })
+    }
+}
+
+/// Capitalise the first letter of each word in `text` and lowercase the rest
+pub fn title_case(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut start = true;
+    for c in text.chars() {
+        if start {
+            out.extend(c.to_uppercase());
+        } else {
+            out.extend(c.to_lowercase());
+        }
+        start = c.is_whitespace();
+    }
+    out
+}
+
+/// Expand tabs into spaces and strip (or escape) any other control characters, these would
+/// otherwise move the cursor around and mess up the width of the output.
+///
+/// `WIDE_TAIL` is replaced with `�` as well, so that it can't be mistaken for the second column of
+/// a wide character.
+pub fn sanitize(text: &str, tab_width: usize, control_chars: ControlChars) -> String {
+    let mut out = String::with_capacity(text.len());
+    // The column that the next character will be put in, this is needed to find the next tab stop
+    let mut col = 0;
+    for c in text.chars() {
+        if c == '\t' {
+            let spaces = tab_width - col % tab_width.max(1);
+            out.extend(std::iter::repeat_n(' ', spaces));
+            col += spaces;
+        } else if c == WIDE_TAIL {
+            out.push(char::REPLACEMENT_CHARACTER);
+            col += 1;
+        } else if c.is_control() {
+            if control_chars == ControlChars::Escape {
+                let escaped = match c {
+                    '\x7f' => String::from("^?"),
+                    '\0'..='\x1f' => format!("^{}", (c as u8 + b'@') as char),
+                    _ => c.escape_unicode().to_string(),
+                };
+                col += escaped.len();
+                out += &escaped;
+            }
+        } else {
+            out.push(c);
+            col += 1;
+        }
+    }
+    out
+}
+
+/// Reorder `text` from the order that it is stored in to the order that it is shown in, this means
+/// that right-to-left runs of text are reversed.
+///
+/// This is done on the whole string rather than each output, so taking a substring of the result
+/// keeps the characters in the order that they're read.
+pub fn visual_order(text: &str, direction: Direction) -> String {
+    let level = match direction {
+        Direction::Ltr => Level::ltr(),
+        Direction::Rtl => Level::rtl(),
+    };
+    let info = BidiInfo::new(text, Some(level));
+    if !info.has_rtl() {
+        return text.to_string();
+    }
+
+    info.paragraphs
+        .iter()
+        .map(|para| info.reorder_line(para, para.range.clone()))
+        .collect()
+}
+
+/// Turn backslash escapes (`\\r`, `\\n`, `\\t`, `\\\\`, `\\0` and `\\xNN`) in `text` into the
+/// characters that they stand for, anything else is left as it is
+#[cfg(feature = "hardware")]
+pub fn unescape(text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        let rest = chars.as_str();
+        match chars.next() {
+            Some('r') => out.push(b'\r'),
+            Some('n') => out.push(b'\n'),
+            Some('t') => out.push(b'\t'),
+            Some('0') => out.push(0),
+            Some('\\') => out.push(b'\\'),
+            Some('x') => match rest.get(1..3).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                Some(byte) => {
+                    out.push(byte);
+                    chars.nth(1);
+                }
+                None => out.extend_from_slice(b"\\x"),
+            },
+            Some(c) => {
+                out.push(b'\\');
+                let mut buf = [0; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+            None => out.push(b'\\'),
+        }
+    }
+    out
+}
+
+/// Remove the escape codes (i.e. from `--bold`) from `text`, leaving only what is shown
+pub fn strip_escapes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // The code carries on until a letter (or any other final byte, i.e. `@` or `~`)
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// The number of characters that are shown for `text`, which doesn't include any escape codes
+pub fn visible_len(text: &str) -> usize {
+    if text.contains('\x1b') {
+        strip_escapes(text).chars().count()
+    } else {
+        text.chars().count()
+    }
+}
+
+/// Cut `text` down to `width` characters, replacing the end with `ellipsis` if anything was
+/// removed
+pub fn truncate(text: &str, width: usize, ellipsis: &str) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+
+    let keep = width.saturating_sub(ellipsis.chars().count());
+    text.chars()
+        .take(keep)
+        .chain(ellipsis.chars())
+        .take(width)
+        .collect()
+}
+
+/// Parse the characters for `--progress-chars`, which is the filled character and then the empty
+/// one
+pub fn parse_progress_chars(s: &str) -> Result<(char, char), String> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(filled), Some(empty), None) => Ok((filled, empty)),
+        _ => Err(String::from(
+            "expected two characters, the filled one and then the empty one",
+        )),
+    }
+}
+
+/// A progress bar that is `percent` full and `width` characters wide (not including the brackets
+/// and the percentage), i.e. ` [#####----] 52%`
+pub fn progress_bar(percent: f64, width: usize, filled: char, empty: char) -> String {
+    let percent = percent.clamp(0.0, 100.0);
+    let full = ((percent / 100.0) * width as f64).round() as usize;
+    format!(
+        " [{}{}] {:>3}%",
+        std::iter::repeat_n(filled, full).collect::<String>(),
+        std::iter::repeat_n(empty, width - full).collect::<String>(),
+        percent.round()
+    )
+}
+
+/// The frames of each style of `--spinner`, every frame of a style is the same width
+pub fn spinner_frames(spinner: Spinner) -> &'static [&'static str] {
+    match spinner {
+        Spinner::Braille => &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+        Spinner::Dots => &[".  ", ".. ", "...", "   "],
+        Spinner::Line => &["-", "\\", "|", "/"],
+    }
+}
+
+/// How long ago something happened in its largest whole unit (i.e. `5s`, `3m` or `2h`), this is
+/// what `{age}` is replaced with in the prefix and suffix
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+/// The number of frames that each step is split into with `--smooth`
+pub const SMOOTH_STEPS: usize = 8;
+
+/// Replace the characters at either end of `text` with blocks that show the content `eighths` of
+/// the way to its next step (see `--smooth`).
+///
+/// The character coming in grows from the edge, and the one going out shrinks towards the other
+/// edge.  There are only half and eighth blocks that are on the right of a cell, so those sides
+/// are less smooth.
+pub fn smooth_edges(text: &str, eighths: usize, reverse: bool) -> String {
+    const LEFT: [char; SMOOTH_STEPS] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let right = |eighths| match eighths {
+        0 => ' ',
+        1..=3 => '▕',
+        _ => '▐',
+    };
+
+    let mut chars: Vec<char> = text.chars().collect();
+    if chars.len() < 2 {
+        return text.to_string();
+    }
+    let last = chars.len() - 1;
+    if reverse {
+        // Moving to the right, so the content comes in from the left
+        chars[0] = right(eighths);
+        chars[last] = LEFT[SMOOTH_STEPS - eighths];
+    } else {
+        chars[0] = right(SMOOTH_STEPS - eighths);
+        chars[last] = LEFT[eighths];
+    }
+    chars.into_iter().collect()
+}
+
+/// Pad `text` with `pad_char` on either side so that it is (at least) `width` characters wide
+pub fn pad(text: &str, width: usize, align: Align, pad_char: char) -> String {
+    let space = width.saturating_sub(visible_len(text));
+    let (left, right) = match align {
+        Align::Left => (0, space),
+        Align::Center => (space / 2, space - space / 2),
+        Align::Right => (space, 0),
+    };
+    let pad = |n| std::iter::repeat_n(pad_char, n).collect::<String>();
+    format!("{}{}{}", pad(left), text, pad(right))
+}
+
+/// Flip `text` horizontally, reversing the characters and swapping any that have a mirrored form
+/// for that form
+pub fn mirror(text: &str) -> String {
+    const PAIRS: [(char, char); 10] = [
+        ('(', ')'),
+        ('[', ']'),
+        ('{', '}'),
+        ('<', '>'),
+        ('/', '\\'),
+        ('«', '»'),
+        ('‹', '›'),
+        ('⟨', '⟩'),
+        ('⁅', '⁆'),
+        ('≤', '≥'),
+    ];
+    text.chars()
+        .rev()
+        .map(|c| {
+            PAIRS
+                .iter()
+                .find_map(|&(a, b)| {
+                    if c == a {
+                        Some(b)
+                    } else if c == b {
+                        Some(a)
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or(c)
+        })
+        .collect()
+}
+
+/// Wrap `text` onto lines that are at most `width` characters long, lines are broken at whitespace
+/// where possible and words longer than `width` are split.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_len = 0;
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        // Split up words that will never fit on a line
+        while word.len() > width {
+            if line_len > 0 {
+                lines.push(std::mem::take(&mut line));
+            }
+            lines.push(word.drain(..width).collect());
+            line_len = 0;
+        }
+
+        if line_len > 0 && line_len + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+            line_len = 0;
+        }
+        if line_len > 0 {
+            line.push(' ');
+            line_len += 1;
+        }
+        line.extend(word.iter());
+        line_len += word.len();
+    }
+    if line_len > 0 || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Find the index of every character in `text` that starts a word (a non-whitespace character
+/// that comes after whitespace), `start` is always included so that the content can be shown from
+/// its beginning.
+pub fn word_starts(text: &[char], start: usize) -> Vec<usize> {
+    (0..text.len())
+        .filter(|&i| {
+            i == start || (!text[i].is_whitespace() && (i == 0 || text[i - 1].is_whitespace()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace(expr: &str, text: &str) -> String {
+        expr.parse::<Replace>().unwrap().apply(text)
+    }
+
+    #[test]
+    fn replace_first_or_every_match() {
+        assert_eq!(replace("s/o/0/", "foo boo"), "f0o boo");
+        assert_eq!(replace("s/o/0/g", "foo boo"), "f00 b00");
+    }
+
+    #[test]
+    fn replace_case_insensitive() {
+        assert_eq!(replace("s/foo/bar/i", "FOO foo"), "bar foo");
+        assert_eq!(replace("s/foo/bar/gi", "FOO foo"), "bar bar");
+    }
+
+    #[test]
+    fn replace_other_delimiter() {
+        assert_eq!(replace("s|/usr|~|", "/usr/bin"), "~/bin");
+        assert_eq!(replace("s#a#b#g", "aaa"), "bbb");
+    }
+
+    #[test]
+    fn replace_escaped_delimiter() {
+        assert_eq!(replace(r"s/\//-/g", "a/b/c"), "a-b-c");
+        // Other escapes are passed through to the regex
+        assert_eq!(replace(r"s/\d+/N/g", "a1b22"), "aNbN");
+    }
+
+    #[test]
+    fn replace_group_references() {
+        assert_eq!(
+            replace(r"s/(\w+) (\w+)/\2 \1/", "hello world"),
+            "world hello"
+        );
+        // A group reference followed by a word character is still just the group
+        assert_eq!(replace(r"s/(a)/\1b/", "a"), "ab");
+    }
+
+    #[test]
+    fn replace_invalid() {
+        assert!("".parse::<Replace>().is_err());
+        assert!("y/a/b/".parse::<Replace>().is_err());
+        assert!("s".parse::<Replace>().is_err());
+        assert!("s/a/b".parse::<Replace>().is_err());
+        assert!("s/a/b/c/".parse::<Replace>().is_err());
+        assert!("s/a/b/x".parse::<Replace>().is_err());
+        assert!("s/(/b/".parse::<Replace>().is_err());
+    }
+}
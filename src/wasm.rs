@@ -0,0 +1,51 @@
+//! JavaScript bindings for working out the frames of a marquee, this is only built for `wasm32`.
+//!
+//! The module is built with `cargo rustc --release --lib --crate-type cdylib --target
+//! wasm32-unknown-unknown`, and then `wasm-bindgen --target web` to generate the JavaScript.
+//! Nothing is printed, and the page has to wait `delay()` between each frame itself.
+
+use clap::Parser;
+use wasm_bindgen::prelude::*;
+
+use crate::{figlet::Font, marquee, Cli};
+
+/// A marquee that the frames can be taken from one at a time
+#[wasm_bindgen(js_name = Marquee)]
+pub struct Marquee {
+    marquee: marquee::Marquee,
+    delay: f64,
+}
+
+#[wasm_bindgen(js_class = Marquee)]
+impl Marquee {
+    /// Create a marquee from command line arguments (without the name of the program), which are
+    /// the same as the ones that the `marquee` command takes (i.e. `["--width", "20"]`)
+    #[wasm_bindgen(constructor)]
+    pub fn new(args: Vec<String>) -> Result<Marquee, JsError> {
+        let options = Cli::try_parse_from(std::iter::once(String::from("marquee")).chain(args))?;
+        let font = Font::from_options(&options)?;
+        Ok(Self {
+            delay: options.frame_delay().as_secs_f64() * 1000.0,
+            marquee: marquee::Marquee::new(options, font),
+        })
+    }
+
+    /// Show `text` on the marquee, this is the same as a line of input to the `marquee` command
+    /// (so it is JSON with `--json`, and an empty string clears it)
+    #[wasm_bindgen(js_name = setText)]
+    pub fn set_text(&mut self, text: String) {
+        self.marquee.push(text, None);
+    }
+
+    /// Work out the next frame, the rows are joined with `\n`.  Returns `undefined` if there is
+    /// nothing to show.
+    #[wasm_bindgen(js_name = nextFrame)]
+    pub fn next_frame(&mut self) -> Result<Option<String>, JsError> {
+        Ok(self.marquee.frame()?.map(|frame| frame.rows.join("\n")))
+    }
+
+    /// The number of milliseconds to wait between each frame
+    pub fn delay(&self) -> f64 {
+        self.delay
+    }
+}
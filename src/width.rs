@@ -0,0 +1,169 @@
+//! How many columns each character takes up in the terminal (see `--probe-widths`)
+//!
+//! Terminals don't agree on how wide emoji and the characters with an ambiguous width (i.e. `…`)
+//! are, so they are printed to the terminal once and it is asked where the cursor ended up.
+
+use std::io;
+
+use unicode_width::UnicodeWidthChar;
+
+/// The character that is put after each wide character, so that every character of the content
+/// takes up one column (see `Widths::cells`).  It is a noncharacter, which shouldn't be shown, so
+/// it is replaced when it is part of the content (see `sanitize`).
+pub const WIDE_TAIL: char = '\u{FDD0}';
+
+/// A character with an ambiguous width, which is two columns wide in most CJK locales
+const AMBIGUOUS: char = '…';
+/// An emoji, which is two columns wide in most (but not all) terminals
+const EMOJI: char = '😀';
+
+/// The widths that were measured in the terminal
+#[derive(Debug, Clone, Copy)]
+pub struct Widths {
+    ambiguous: usize,
+    emoji: usize,
+}
+
+impl Widths {
+    /// Measure the widths in the terminal (`/dev/tty`), this only works on unix
+    #[cfg(unix)]
+    pub fn probe() -> io::Result<Self> {
+        use std::fs::OpenOptions;
+
+        let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let saved = stty(&tty, &["-g"])?;
+        // Read the reply a byte at a time without it being echoed, giving up after half a second
+        stty(&tty, &["raw", "-echo", "min", "0", "time", "5"])?;
+        let widths = measure(&mut tty, AMBIGUOUS)
+            .and_then(|ambiguous| Ok((ambiguous, measure(&mut tty, EMOJI)?)));
+        let restored = io::Write::write_all(&mut tty, b"\r\x1b[2K")
+            .and_then(|_| stty(&tty, &[saved.trim()]).map(|_| ()));
+        let (ambiguous, emoji) = widths?;
+        restored?;
+        log::info!(
+            "Ambiguous characters are {} wide, and emoji are {} wide",
+            ambiguous,
+            emoji
+        );
+        Ok(Self { ambiguous, emoji })
+    }
+
+    #[cfg(not(unix))]
+    pub fn probe() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "probing the widths is only supported on unix",
+        ))
+    }
+
+    /// Widths that are already known, rather than measured in the terminal
+    #[cfg(test)]
+    pub fn new(ambiguous: usize, emoji: usize) -> Self {
+        Self { ambiguous, emoji }
+    }
+
+    /// The number of columns that `c` takes up, which is zero for combining marks (which are drawn
+    /// on top of the character before them), and otherwise one or two
+    pub fn of(&self, c: char) -> usize {
+        let narrow = c.width().unwrap_or(1);
+        if narrow != c.width_cjk().unwrap_or(1) {
+            self.ambiguous
+        } else if narrow == 2 && matches!(c as u32, 0x1f000..=0x1faff) {
+            self.emoji
+        } else {
+            narrow.min(2)
+        }
+    }
+
+    /// If `c` starts a column of `cells`, rather than being drawn in the one before it
+    pub fn starts_column(&self, c: char) -> bool {
+        c == WIDE_TAIL || self.of(c) > 0
+    }
+
+    /// The number of columns that `s` takes up
+    pub fn width(&self, s: &str) -> usize {
+        s.chars().map(|c| self.of(c)).sum()
+    }
+
+    /// `s` with `WIDE_TAIL` after each wide character, so that it can be cut up by characters
+    /// without a wide character being counted as one column
+    pub fn cells(&self, s: &str) -> String {
+        let mut out = String::with_capacity(s.len());
+        for c in s.chars() {
+            out.push(c);
+            if self.of(c) == 2 {
+                out.push(WIDE_TAIL);
+            }
+        }
+        out
+    }
+
+    /// Replace the halves of wide characters that were cut in half (in `cells`) with spaces, so
+    /// that the row is still the same width
+    pub fn fit(&self, row: &str) -> String {
+        let mut out = String::with_capacity(row.len());
+        let mut chars = row.chars().peekable();
+        let mut prev_wide = false;
+        while let Some(c) = chars.next() {
+            let wide = c != WIDE_TAIL && self.of(c) == 2;
+            out.push(match c {
+                WIDE_TAIL if !prev_wide => ' ',
+                _ if wide && chars.peek() != Some(&WIDE_TAIL) => ' ',
+                c => c,
+            });
+            prev_wide = wide;
+        }
+        out
+    }
+}
+
+/// Run `stty` on the terminal, returning what it printed
+#[cfg(unix)]
+fn stty(tty: &std::fs::File, args: &[&str]) -> io::Result<String> {
+    let output = std::process::Command::new("stty")
+        .args(args)
+        .stdin(tty.try_clone()?)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "stty exited with {}",
+            output.status
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Print `c` at the start of the line, and work out how wide it is from where the cursor ends up
+#[cfg(unix)]
+fn measure(tty: &mut std::fs::File, c: char) -> io::Result<usize> {
+    use std::io::{Read, Write};
+
+    write!(tty, "\r{}\x1b[6n", c)?;
+    tty.flush()?;
+    // The reply is `ESC [ row ; column R`
+    let mut reply = Vec::new();
+    let mut byte = [0];
+    loop {
+        if tty.read(&mut byte)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the terminal didn't reply with the position of the cursor",
+            ));
+        }
+        if byte[0] == b'R' {
+            break;
+        }
+        reply.push(byte[0]);
+    }
+    let column = String::from_utf8_lossy(&reply)
+        .rsplit(';')
+        .next()
+        .and_then(|column| column.parse::<usize>().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the terminal replied with an invalid cursor position",
+            )
+        })?;
+    Ok(column.saturating_sub(1).clamp(1, 2))
+}
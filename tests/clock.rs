@@ -0,0 +1,128 @@
+//! Golden output of `--clock external`, where a frame is shown for each line of the tick file so
+//! that the output is the same every time
+
+use std::{
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+/// Run the marquee with `args` and `input` on stdin, and a tick file with `ticks` lines in it
+fn run(name: &str, args: &[&str], input: &str, ticks: usize) -> Output {
+    let tick_file: PathBuf = env::temp_dir().join(format!(
+        "marquee-test-{}-{}.ticks",
+        name,
+        std::process::id()
+    ));
+    fs::write(&tick_file, "\n".repeat(ticks)).expect("the tick file is written");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_marquee"))
+        .args(["--clock", "external", "--animate", "always", "--tick-file"])
+        .arg(&tick_file)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("the marquee starts");
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(input.as_bytes())
+        .expect("the input is written");
+    let output = child.wait_with_output().expect("the marquee runs");
+    let _ = fs::remove_file(&tick_file);
+    output
+}
+
+/// The lines that were printed, checking that the marquee exited successfully
+fn frames(output: &Output) -> Vec<&str> {
+    assert!(
+        output.status.success(),
+        "exited with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    std::str::from_utf8(&output.stdout)
+        .expect("the output is UTF-8")
+        .lines()
+        .collect()
+}
+
+#[test]
+fn one_frame_per_tick() {
+    let output = run("one_frame_per_tick", &["-w", "4"], "abcdef\n", 4);
+    assert_eq!(frames(&output), ["abcd", "bcde", "cdef", "def "]);
+}
+
+#[test]
+fn loops_around_the_separator() {
+    let output = run("loops", &["-w", "4", "-s", "|"], "abcdef\n", 9);
+    assert_eq!(
+        frames(&output),
+        ["abcd", "bcde", "cdef", "def|", "ef|a", "f|ab", "|abc", "abcd", "bcde"]
+    );
+}
+
+#[test]
+fn reversed() {
+    let output = run("reversed", &["-w", "4", "-s", "|", "-r"], "abcdef\n", 4);
+    assert_eq!(frames(&output), ["cdef", "bcde", "abcd", "|abc"]);
+}
+
+#[test]
+fn content_that_fits() {
+    // The same frame isn't printed twice in a row
+    let output = run("fits", &["-w", "10"], "abc\n", 3);
+    assert_eq!(frames(&output), ["abc"]);
+}
+
+#[test]
+fn no_loop_stops_early() {
+    let output = run("no_loop", &["-w", "4", "-l"], "abcdef\n", 10);
+    assert_eq!(frames(&output), ["abcd", "bcde", "cdef"]);
+}
+
+#[test]
+fn no_ticks() {
+    let output = run("no_ticks", &["-w", "4"], "abcdef\n", 0);
+    assert_eq!(frames(&output), Vec::<&str>::new());
+}
+
+#[test]
+fn no_input() {
+    let output = run("no_input", &["-w", "4"], "", 3);
+    assert_eq!(frames(&output), Vec::<&str>::new());
+}
+
+/// Run the marquee with `--on-finish exec`, returning if the command was run
+fn on_finish_ran(name: &str, args: &[&str], ticks: usize) -> bool {
+    let marker = env::temp_dir().join(format!(
+        "marquee-test-{}-{}.finished",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&marker);
+    let exec = format!("exec touch '{}'", marker.display());
+    let mut args = args.to_vec();
+    args.extend(["--on-finish", &exec]);
+    frames(&run(name, &args, "abcdef\n", ticks));
+    let ran = marker.exists();
+    let _ = fs::remove_file(&marker);
+    ran
+}
+
+#[test]
+#[cfg(unix)]
+fn on_finish_after_no_loop() {
+    assert!(on_finish_ran("on_finish_no_loop", &["-w", "4", "-l"], 10));
+}
+
+#[test]
+#[cfg(unix)]
+fn on_finish_not_when_the_ticks_run_out() {
+    // The content is still looping, so it hasn't finished
+    assert!(!on_finish_ran("on_finish_ticks", &["-w", "4"], 3));
+}
@@ -0,0 +1,71 @@
+//! The exit code of the marquee when it stops on its own, or because something went wrong
+
+use std::{
+    io::Write,
+    process::{Command, ExitStatus, Stdio},
+};
+
+/// Run the marquee with `args` and `input` on stdin (which is closed afterwards), and wait for it
+/// to exit
+fn run(args: &[&str], input: &str) -> ExitStatus {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_marquee"))
+        .args(["--animate", "always", "-d", "1"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("the marquee starts");
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(input.as_bytes())
+        .expect("the input is written");
+    child.wait().expect("the marquee runs")
+}
+
+#[test]
+fn no_loop() {
+    assert_eq!(run(&["-w", "4", "-l"], "abcdef\n").code(), Some(0));
+}
+
+#[test]
+fn exit_on_eof() {
+    assert_eq!(run(&["-w", "4", "-e"], "abcdef\n").code(), Some(3));
+    assert_eq!(
+        run(&["-w", "4", "-e", "after-cycle"], "abcdef\n").code(),
+        Some(3)
+    );
+}
+
+#[test]
+fn eof_before_anything_is_shown() {
+    assert_eq!(run(&["-w", "4"], "").code(), Some(3));
+}
+
+#[test]
+fn max_runtime() {
+    assert_eq!(
+        run(&["-w", "4", "--max-runtime", "50ms"], "abcdef\n").code(),
+        Some(6)
+    );
+}
+
+#[test]
+fn exit_after_idle() {
+    assert_eq!(
+        run(&["-w", "4", "--exit-after-idle", "50ms"], "abcdef\n").code(),
+        Some(0)
+    );
+}
+
+#[test]
+fn invalid_input() {
+    assert_eq!(run(&["-w", "4", "-j", "--strict"], "{\n").code(), Some(4));
+}
+
+#[test]
+fn invalid_arguments() {
+    assert_eq!(run(&["--width", "wide"], "").code(), Some(2));
+}
@@ -0,0 +1,29 @@
+//! Using the marquee in a pipeline, where stdout isn't a terminal
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+#[test]
+fn no_loop_and_no_delay_animate() {
+    // `marquee -ld0` sends every frame on, even though `--animate` is `auto`
+    let mut child = Command::new(env!("CARGO_BIN_EXE_marquee"))
+        .args(["-ld0", "-w", "4"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("the marquee starts");
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(b"abcdef\n")
+        .expect("the input is written");
+    let output = child.wait_with_output().expect("the marquee runs");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).expect("the output is UTF-8"),
+        "abcd\nbcde\ncdef\n"
+    );
+}